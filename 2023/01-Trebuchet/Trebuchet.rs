@@ -1,88 +1,108 @@
 // Trebuchet
 // https://adventofcode.com/2023/day/1
 
+use std::cmp::Reverse;
+use std::fs::read_to_string;
 use std::panic;
 
-fn read_input() -> Vec<String> {
-    std::fs::read_to_string("input.txt")
-        .unwrap()
-        .lines()
-        .map(str::to_string)
-        .collect()
-}
+#[path = "../common/aho_corasick.rs"]
+mod aho_corasick;
+use aho_corasick::AhoCorasick;
 
-fn get_first_digit(str: &str) -> u32 {
-    for c in str.chars() {
-        if c.is_numeric() {
-            return c.to_digit(10).unwrap();
-        }
-    }
-    panic!("No digit found in {}", str);
+fn parse_input(input_str: &str) -> Vec<&str> {
+    input_str.lines().collect()
 }
 
-fn get_last_digit(str: &str) -> u32 {
-    for c in str.chars().rev() {
-        if c.is_numeric() {
-            return c.to_digit(10).unwrap();
-        }
-    }
-    panic!("No digit found in {}", str);
-}
+// Words mapped to the value they stand for. Matching is case sensitive.
+type Vocabulary = Vec<(String, u32)>;
+
+const DIGIT_WORDS: [&str; 10] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
 
-fn calibration_value(str: &str) -> u32 {
-    let msd = get_first_digit(str);
-    let lsd = get_last_digit(str);
-    msd * 10 + lsd
+fn digits() -> Vocabulary {
+    (0..10).map(|digit| (digit.to_string(), digit)).collect()
 }
 
-fn solve_part1(input: &[String]) -> u32 {
-    input.iter().map(|line| calibration_value(line)).sum()
+// Words for consecutive values starting from `first`.
+fn word_values(words: &[&str], first: u32) -> Vocabulary {
+    words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| (word.to_string(), first + i as u32))
+        .collect()
 }
 
-fn calibration_value_part2(str: &str) -> u32 {
-    let mut digits = Vec::new();
-    for (i, c) in str.chars().enumerate() {
-        if c.is_numeric() {
-            digits.push(c.to_digit(10).unwrap());
-        } else {
-            let sub = &str[i..];
-            if sub.starts_with("zero") {
-                digits.push(0);
-            } else if sub.starts_with("one") {
-                digits.push(1);
-            } else if sub.starts_with("two") {
-                digits.push(2);
-            } else if sub.starts_with("three") {
-                digits.push(3);
-            } else if sub.starts_with("four") {
-                digits.push(4);
-            } else if sub.starts_with("five") {
-                digits.push(5);
-            } else if sub.starts_with("six") {
-                digits.push(6);
-            } else if sub.starts_with("seven") {
-                digits.push(7);
-            } else if sub.starts_with("eight") {
-                digits.push(8);
-            } else if sub.starts_with("nine") {
-                digits.push(9);
+// Loads a vocabulary with one "word value" pair per line, skipping blank lines.
+fn parse_vocabulary(text: &str) -> Result<Vocabulary, String> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(word), Some(value), None) => value
+                    .parse()
+                    .map(|value| (word.to_string(), value))
+                    .map_err(|_| format!("Invalid value in vocabulary line: {}", line)),
+                _ => Err(format!(
+                    "Expected \"word value\" in vocabulary line: {}",
+                    line
+                )),
             }
-        }
+        })
+        .collect()
+}
+
+// Combines the first and last values found in the line. Matches may overlap, so "eightwo"
+// is 8 then 2. When two words start at the same place the longer one wins, so with a
+// vocabulary containing both "six" and "sixteen" the line "sixteen" reads as 16.
+fn calibration_value(line: &str, matcher: &AhoCorasick<u32>) -> u32 {
+    let matches = matcher.find_overlapping(line);
+    let first = matches.iter().min_by_key(|m| (m.start, Reverse(m.end)));
+    let last = matches.iter().max_by_key(|m| (m.start, m.end));
+    match (first, last) {
+        (Some(first), Some(last)) => first.value * 10 + last.value,
+        _ => panic!("No digit found in {}", line),
     }
-    let msd = digits.first().unwrap();
-    let lsd = digits.last().unwrap();
-    msd * 10 + lsd
 }
 
-fn solve_part2(input: &[String]) -> u32 {
-    input.iter().map(|line| calibration_value_part2(line)).sum()
+fn solve(input: &[&str], vocabulary: Vocabulary) -> u32 {
+    let matcher = AhoCorasick::new(vocabulary);
+    input
+        .iter()
+        .map(|line| calibration_value(line, &matcher))
+        .sum()
+}
+
+fn solve_part1(input: &[&str]) -> u32 {
+    solve(input, digits())
+}
+
+fn solve_part2(input: &[&str]) -> u32 {
+    let mut vocabulary = digits();
+    vocabulary.extend(word_values(&DIGIT_WORDS, 0));
+    solve(input, vocabulary)
 }
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = read_input();
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
+    // Read the calibration with digits plus the words in an alternate vocabulary file.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--vocabulary") {
+        let path = args.get(1).expect("Usage: --vocabulary <file>");
+        let text = read_to_string(path).unwrap();
+        let mut vocabulary = digits();
+        vocabulary.extend(parse_vocabulary(&text).unwrap_or_else(|error| panic!("{}", error)));
+        let start = std::time::Instant::now();
+        let calibration = solve(&input, vocabulary);
+        println!("Calibration: {} ({:?})", calibration, start.elapsed());
+        return;
+    }
+
     let part1_start = std::time::Instant::now();
     let part1 = solve_part1(&input);
     println!("Part 1: {} ({:?})", part1, part1_start.elapsed());
@@ -94,24 +114,18 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use solve_part1;
-    use solve_part2;
+    use super::*;
 
     #[test]
     fn test_part1() {
-        let input: Vec<_> = [
-            "1abc2",
-            "pqr3stu8vwx",
-            "a1b2c3d4e5f",
-            "treb7uchet",
-        ].iter().map(|s| s.to_string()).collect();
+        let input = ["1abc2", "pqr3stu8vwx", "a1b2c3d4e5f", "treb7uchet"];
         let result = solve_part1(&input);
         assert_eq!(result, 142)
     }
 
     #[test]
     fn test_part2() {
-        let input: Vec<_> = [
+        let input = [
             "two1nine",
             "eightwothree",
             "abcone2threexyz",
@@ -119,8 +133,70 @@ mod tests {
             "4nineeightseven2",
             "zoneight234",
             "7pqrstsixteen",
-        ].iter().map(|s| s.to_string()).collect();
+        ];
         let result = solve_part2(&input);
         assert_eq!(result, 281)
     }
+
+    #[test]
+    fn test_overlapping_words() {
+        let mut vocabulary = digits();
+        vocabulary.extend(word_values(&DIGIT_WORDS, 0));
+        let matcher = AhoCorasick::new(vocabulary);
+        assert_eq!(calibration_value("eightwo", &matcher), 82);
+        assert_eq!(calibration_value("oneight", &matcher), 18);
+        assert_eq!(calibration_value("twone", &matcher), 21);
+    }
+
+    #[test]
+    fn test_utf8() {
+        // Words only match whole characters, so "sïx" is not a six.
+        assert_eq!(
+            solve_part2(&["über2sïx", "ñ3é4", "fünf5nine"]),
+            22 + 34 + 59
+        );
+        assert_eq!(solve_part1(&["日本1語9"]), 19);
+    }
+
+    #[test]
+    fn test_alternate_vocabularies() {
+        let german = [
+            "null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+        ];
+        let mut vocabulary = digits();
+        vocabulary.extend(word_values(&german, 0));
+        assert_eq!(
+            solve(&["fünfzweiacht", "xsiebenundneunzig"], vocabulary),
+            58 + 79
+        );
+
+        let teens = [
+            "ten",
+            "eleven",
+            "twelve",
+            "thirteen",
+            "fourteen",
+            "fifteen",
+            "sixteen",
+            "seventeen",
+            "eighteen",
+            "nineteen",
+            "twenty",
+        ];
+        let mut vocabulary = word_values(&DIGIT_WORDS, 0);
+        vocabulary.extend(word_values(&teens, 10));
+        let matcher = AhoCorasick::new(vocabulary);
+        assert_eq!(calibration_value("sixteen", &matcher), 16 * 10 + 16);
+        assert_eq!(calibration_value("twentyseven", &matcher), 20 * 10 + 7);
+    }
+
+    #[test]
+    fn test_parse_vocabulary() {
+        let vocabulary = parse_vocabulary("un 1\ndeux 2\n\ntrois 3\n").unwrap();
+        assert_eq!(vocabulary.len(), 3);
+        assert_eq!(vocabulary[1], ("deux".to_string(), 2));
+        assert_eq!(solve(&["deuxtroisun"], vocabulary), 21);
+        assert!(parse_vocabulary("un").is_err());
+        assert!(parse_vocabulary("un one").is_err());
+    }
 }
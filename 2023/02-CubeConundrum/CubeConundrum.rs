@@ -2,135 +2,281 @@
 // https://adventofcode.com/2023/day/2
 
 use std::cmp::max;
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
 use std::panic;
 
-type InputType = Vec<Game>;
+#[path = "../common/parser.rs"]
+mod parser;
+use parser::{
+    delimited, identifier, literal, map, pair, parse_all, separated1, spaces1, terminated,
+    unsigned, Parser,
+};
+
+type InputType<'a> = Vec<Game<'a>>;
 type SolutionType = u32;
 
+// A multiset of cubes: how many there are of each colour. Colours are whatever names
+// the input uses, and a colour that is not mentioned has no cubes.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Cubes<'a> {
+    counts: BTreeMap<&'a str, u32>,
+}
+
+impl<'a> Cubes<'a> {
+    fn count(&self, color: &str) -> u32 {
+        self.counts.get(color).copied().unwrap_or(0)
+    }
+
+    fn add(&mut self, color: &'a str, count: u32) {
+        *self.counts.entry(color).or_insert(0) += count;
+    }
+
+    fn total(&self) -> u32 {
+        self.counts.values().sum()
+    }
+
+    // True if every cube here could have come out of the bag.
+    fn fits_in(&self, bag: &Cubes) -> bool {
+        self.counts
+            .iter()
+            .all(|(color, &count)| count <= bag.count(color))
+    }
+
+    // The smallest multiset containing both this and `other`.
+    fn union(&self, other: &Cubes<'a>) -> Cubes<'a> {
+        let mut result = self.clone();
+        for (&color, &count) in &other.counts {
+            let entry = result.counts.entry(color).or_insert(0);
+            *entry = max(*entry, count);
+        }
+        result
+    }
+
+    // The counts of the given colours multiplied together, so a missing colour gives 0.
+    fn power(&self, colors: &[&str]) -> u32 {
+        colors.iter().map(|color| self.count(color)).product()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Game<'a> {
+    id: u32,
+    draws: Vec<Cubes<'a>>,
+}
+
 #[derive(Debug, PartialEq)]
-struct Cubes {
-    red: u32,
-    green: u32,
-    blue: u32,
-}
-
-type Game = Vec<Cubes>;
-
-fn parse_cube(line: &str) -> Cubes {
-    line.split(", ").fold(Cubes { red: 0, green: 0, blue: 0 }, |result, cube| {
-        let mut parts = cube.split(' ');
-        let count = parts.next().unwrap().parse::<u32>().unwrap();
-        let color = parts.next().unwrap();
-        match color {
-            "red" => Cubes { red: count, ..result },
-            "green" => Cubes { green: count, ..result },
-            "blue" => Cubes { blue: count, ..result },
-            _ => panic!("Unknown color {}", color),
+struct GameStats<'a> {
+    // The fewest cubes of each colour the bag could have held.
+    minimum_bag: Cubes<'a>,
+    // The most cubes shown in a single draw.
+    largest_draw: u32,
+}
+
+impl<'a> Game<'a> {
+    fn stats(&self) -> GameStats<'a> {
+        GameStats {
+            minimum_bag: self
+                .draws
+                .iter()
+                .fold(Cubes::default(), |bag, draw| bag.union(draw)),
+            largest_draw: self.draws.iter().map(Cubes::total).max().unwrap_or(0),
         }
+    }
+}
+
+impl<'a> GameStats<'a> {
+    // Whether the game could have been played with each of the bags.
+    fn possible_with(&self, bags: &[Cubes]) -> Vec<bool> {
+        bags.iter()
+            .map(|bag| self.minimum_bag.fits_in(bag))
+            .collect()
+    }
+}
+
+// The colours whose product is a bag's power in part 2.
+const POWER_COLORS: [&str; 3] = ["red", "green", "blue"];
+
+const DEFAULT_BAG: &str = "12 red, 13 green, 14 blue";
+
+// 3 blue, 4 red
+fn cubes<'a>() -> impl Parser<'a, Cubes<'a>> {
+    let cube = pair(terminated(unsigned::<u32>(), spaces1()), identifier());
+    map(separated1(cube, literal(", ")), |cubes| {
+        cubes
+            .into_iter()
+            .fold(Cubes::default(), |mut result, (count, color)| {
+                result.add(color, count);
+                result
+            })
     })
 }
 
-fn parse_line(line: &str) -> Game {
-    line.split(": ").nth(1).unwrap()
-        .split("; ")
-        .map(parse_cube)
-        .collect()
+// Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+fn game<'a>() -> impl Parser<'a, Game<'a>> {
+    let game_number = delimited(literal("Game "), unsigned::<u32>(), literal(": "));
+    map(
+        pair(game_number, separated1(cubes(), literal("; "))),
+        |(id, draws)| Game { id, draws },
+    )
 }
 
-fn read_input() -> InputType {
-    std::fs::read_to_string("input.txt")
-        .unwrap()
-        .lines()
-        .map(parse_line)
-        .collect()
+fn parse_line(line: &str) -> Game<'_> {
+    parse_all(game(), line).unwrap_or_else(|error| panic!("{}", error))
 }
 
-fn is_possible(game: &Game, bag: &Cubes) -> bool {
-    game.iter().all(|cubes| {
-        cubes.red <= bag.red && cubes.green <= bag.green && cubes.blue <= bag.blue
-    })
+fn parse_bag(bag: &str) -> Cubes<'_> {
+    parse_all(cubes(), bag).unwrap_or_else(|error| panic!("{}", error))
 }
 
-fn solve_part1(input: &InputType) -> SolutionType {
-    let bag = Cubes { red: 12, green: 13, blue: 14 };
-    input.iter().enumerate().fold(0, |result, (index, game)| {
-        if is_possible(game, &bag) { 
-            result + index as u32 + 1
-        } else {
-            result
-        }
-    })
+fn parse_input(input_str: &str) -> InputType<'_> {
+    input_str.lines().map(parse_line).collect()
 }
 
-fn minimum_cubes(game: &Game) -> Cubes {
-    game.iter().fold(Cubes { red: 0, green: 0, blue: 0 }, |result, cubes| {
-        Cubes {
-            red: max(result.red, cubes.red),
-            green: max(result.green, cubes.green),
-            blue: max(result.blue, cubes.blue),
+// The number of games possible with each bag, and the sum of their ids.
+fn possible_games(input: &InputType, bags: &[Cubes]) -> Vec<(usize, SolutionType)> {
+    let mut result = vec![(0, 0); bags.len()];
+    for game in input {
+        for (possible, total) in game
+            .stats()
+            .possible_with(bags)
+            .iter()
+            .zip(result.iter_mut())
+        {
+            if *possible {
+                total.0 += 1;
+                total.1 += game.id;
+            }
         }
-    })
+    }
+    result
 }
 
-fn power(cubes: Cubes) -> u32 {
-    cubes.red * cubes.green * cubes.blue
+fn solve_part1(input: &InputType, bag: &Cubes) -> SolutionType {
+    possible_games(input, std::slice::from_ref(bag))[0].1
 }
 
 fn solve_part2(input: &InputType) -> SolutionType {
-    input.iter().map(|game| {
-        power(minimum_cubes(game))
-    }).sum()
+    input
+        .iter()
+        .map(|game| game.stats().minimum_bag.power(&POWER_COLORS))
+        .sum()
 }
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = read_input();
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
+    // Bags to check the games against, such as "12 red, 13 green, 14 blue", can be given
+    // on the command line. Part 1 uses the first.
+    let bag_strs: Vec<String> = std::env::args().skip(1).collect();
+    let bags: Vec<Cubes> = if bag_strs.is_empty() {
+        vec![parse_bag(DEFAULT_BAG)]
+    } else {
+        bag_strs.iter().map(|bag| parse_bag(bag)).collect()
+    };
+
     let part1_start = std::time::Instant::now();
-    let part1 = solve_part1(&input);
+    let part1 = solve_part1(&input, &bags[0]);
     println!("Part 1: {} ({:?})", part1, part1_start.elapsed());
 
     let part2_start = std::time::Instant::now();
     let part2 = solve_part2(&input);
     println!("Part 2: {} ({:?})", part2, part2_start.elapsed());
+
+    if bags.len() > 1 {
+        for (bag, (count, id_sum)) in bag_strs.iter().zip(possible_games(&input, &bags)) {
+            println!("{}: {} possible games, ids sum to {}", bag, count, id_sum);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const SAMPLE_INPUT: &str = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
+
+    fn cubes_of<'a>(counts: &[(&'a str, u32)]) -> Cubes<'a> {
+        let mut cubes = Cubes::default();
+        for &(color, count) in counts {
+            cubes.add(color, count);
+        }
+        cubes
+    }
+
     #[test]
     fn test_parse_line() {
         let input = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green";
         let result = parse_line(input);
-        assert_eq!(result, vec![
-            Cubes { red: 4, green: 0, blue: 3 },
-            Cubes { red: 1, green: 2, blue: 6 },
-            Cubes { red: 0, green: 2, blue: 0 },
-        ])
+        assert_eq!(result.id, 1);
+        assert_eq!(
+            result.draws,
+            vec![
+                cubes_of(&[("red", 4), ("blue", 3)]),
+                cubes_of(&[("red", 1), ("green", 2), ("blue", 6)]),
+                cubes_of(&[("green", 2)]),
+            ]
+        )
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = parse_all(game(), "Game 1: 3 blue, four red").unwrap_err();
+        assert_eq!(error.column, 17);
+        assert_eq!(error.expected, "unsigned integer");
+        let error = parse_all(game(), "Game 1: 3 blue 4 red").unwrap_err();
+        assert_eq!(error.column, 16);
+    }
+
+    #[test]
+    fn test_other_colors() {
+        let game = parse_line("Game 7: 2 purple, 1 red; 5 purple, 2 purple");
+        assert_eq!(game.draws[1].count("purple"), 7);
+        let stats = game.stats();
+        assert_eq!(stats.minimum_bag, cubes_of(&[("purple", 7), ("red", 1)]));
+        assert_eq!(stats.minimum_bag.power(&POWER_COLORS), 0);
+        assert_eq!(stats.minimum_bag.power(&["purple", "red"]), 7);
+        let bags = [parse_bag(DEFAULT_BAG), parse_bag("1 red, 7 purple")];
+        assert_eq!(stats.possible_with(&bags), vec![false, true]);
+    }
+
+    #[test]
+    fn test_stats() {
+        let input = parse_input(SAMPLE_INPUT);
+        let stats = input[2].stats();
+        assert_eq!(
+            stats.minimum_bag,
+            cubes_of(&[("red", 20), ("green", 13), ("blue", 6)])
+        );
+        assert_eq!(stats.largest_draw, 34);
+        let bags = [
+            parse_bag(DEFAULT_BAG),
+            parse_bag("20 red, 13 green, 6 blue"),
+        ];
+        assert_eq!(stats.possible_with(&bags), vec![false, true]);
+        assert_eq!(
+            possible_games(&input, &bags),
+            vec![(3, 8), (4, 1 + 2 + 3 + 5)]
+        );
     }
 
     #[test]
     fn test_part1() {
-        let input = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
-Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
-Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
-Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
-Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green"
-            .lines().map(parse_line).collect();
-        let result = solve_part1(&input);
+        let input = parse_input(SAMPLE_INPUT);
+        let result = solve_part1(&input, &parse_bag(DEFAULT_BAG));
         assert_eq!(result, 8)
     }
 
     #[test]
     fn test_part2() {
-        let input = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
-Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
-Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
-Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
-Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green"
-            .lines().map(parse_line).collect();
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part2(&input);
         assert_eq!(result, 2286)
     }
@@ -1,11 +1,10 @@
 // GearRatios
 // https://adventofcode.com/2023/day/3
 
-use std::cmp::max;
 use std::cmp::min;
 use std::fs::read_to_string;
 
-type InputType = Vec<String>;
+type InputType<'a> = Vec<&'a str>;
 type SolutionType = u32;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -22,6 +21,7 @@ struct PartNumber {
 }
 
 impl PartNumber {
+    #[cfg(test)]
     fn is_adjacent(&self, point: Point) -> bool {
         // Convert usize to i32 to avoid underflow.
         let line = point.line as i32;
@@ -45,9 +45,9 @@ impl PartNumber {
 }
 
 #[derive(Debug, PartialEq)]
-struct PartsAndGears {
-    parts: Vec<PartNumber>,
-    gears: Vec<Point>,
+struct Symbol {
+    kind: char,
+    location: Point,
 }
 
 #[derive(Debug, PartialEq)]
@@ -66,136 +66,173 @@ impl Gear {
     }
 }
 
-fn parse_input(input_str: String) -> InputType {
-    input_str.lines().map(str::to_string).collect()
+fn parse_input(input_str: &str) -> InputType<'_> {
+    input_str.lines().collect()
 }
 
 fn is_symbol(c: char) -> bool {
     !c.is_ascii_digit() && c != '.'
 }
 
-// Return true if a character surrounding the box defined by line_num, char_num, and length is not a digit or a period.
-fn is_part_number(input: &InputType, location: Point, length: usize) -> bool {
-    let start = max(0, location.col as i32 - 1) as usize;
-    let end = min(location.col + length + 1, input[0].len());
+// Every number and symbol in the schematic, with the adjacency between them in both directions.
+// Built in one pass, so rules about which numbers touch which symbols are just queries.
+#[derive(Debug)]
+struct SchematicIndex {
+    numbers: Vec<PartNumber>,
+    symbols: Vec<Symbol>,
+    // The indices of the numbers next to each symbol, and of the symbols next to each number.
+    symbol_numbers: Vec<Vec<usize>>,
+    number_symbols: Vec<Vec<usize>>,
+}
 
-    // Check the line above the box.
-    if location.line > 0 {
-        let line = &input[location.line - 1];
-        if line[start..end].chars().any(is_symbol) {
-            return true;
+impl SchematicIndex {
+    fn build(input: &InputType) -> Self {
+        let mut numbers: Vec<PartNumber> = Vec::new();
+        let mut symbols: Vec<Symbol> = Vec::new();
+        // The index of the number covering each cell, if any.
+        let mut owners: Vec<Vec<Option<usize>>> = Vec::new();
+        for (line_num, line) in input.iter().enumerate() {
+            let mut line_owners = vec![None; line.len()];
+            // The value and length of the number being read, if any.
+            let mut number: Option<(SolutionType, usize)> = None;
+            for (col_num, c) in line.chars().chain(std::iter::once('.')).enumerate() {
+                if let Some(digit) = c.to_digit(10) {
+                    let (value, length) = number.unwrap_or((0, 0));
+                    number = Some((value * 10 + digit, length + 1));
+                    line_owners[col_num] = Some(numbers.len());
+                    continue;
+                }
+                if let Some((value, length)) = number.take() {
+                    numbers.push(PartNumber {
+                        value,
+                        location: Point {
+                            line: line_num,
+                            col: col_num - length,
+                        },
+                        length,
+                    });
+                }
+                if is_symbol(c) {
+                    symbols.push(Symbol {
+                        kind: c,
+                        location: Point {
+                            line: line_num,
+                            col: col_num,
+                        },
+                    });
+                }
+            }
+            owners.push(line_owners);
         }
-    }
-    // Check the line below the box.
-    if location.line < input.len() - 1 {
-        let line = &input[location.line + 1];
-        if line[start..end].chars().any(is_symbol) {
-            return true;
+
+        let mut symbol_numbers = Vec::with_capacity(symbols.len());
+        let mut number_symbols = vec![Vec::new(); numbers.len()];
+        for (symbol_index, symbol) in symbols.iter().enumerate() {
+            let Point { line, col } = symbol.location;
+            let mut adjacent: Vec<usize> = Vec::new();
+            for row in &owners[line.saturating_sub(1)..min(line + 2, owners.len())] {
+                let cells = &row[col.saturating_sub(1).min(row.len())..min(col + 2, row.len())];
+                for &number_index in cells.iter().flatten() {
+                    if !adjacent.contains(&number_index) {
+                        adjacent.push(number_index);
+                    }
+                }
+            }
+            adjacent.sort_unstable();
+            for &number_index in &adjacent {
+                number_symbols[number_index].push(symbol_index);
+            }
+            symbol_numbers.push(adjacent);
+        }
+        Self {
+            numbers,
+            symbols,
+            symbol_numbers,
+            number_symbols,
         }
     }
-    // Check the character to the left of the box.
-    let line = input[location.line].as_bytes();
-    if location.col > 0 && line[location.col - 1] as char != '.' {
-        return true;
+
+    // Numbers adjacent to at least one symbol.
+    fn part_numbers(&self) -> Vec<&PartNumber> {
+        self.numbers
+            .iter()
+            .zip(&self.number_symbols)
+            .filter(|(_, symbols)| !symbols.is_empty())
+            .map(|(number, _)| number)
+            .collect()
     }
-    // Check the character to the right of the box.
-    if location.col + length < input[location.line].len()
-        && line[location.col + length] as char != '.'
-    {
-        return true;
+
+    // Numbers adjacent to no symbol at all.
+    #[cfg(test)]
+    fn isolated_numbers(&self) -> Vec<&PartNumber> {
+        self.numbers
+            .iter()
+            .zip(&self.number_symbols)
+            .filter(|(_, symbols)| symbols.is_empty())
+            .map(|(number, _)| number)
+            .collect()
     }
-    false
-}
 
-fn add_if_part_number(
-    input: &InputType,
-    line: usize,
-    col: usize,
-    number_buffer: &String,
-    part_numbers: &mut Vec<PartNumber>,
-) {
-    let length = number_buffer.len();
-    let location = Point {
-        line,
-        col: col - length,
-    };
-    if is_part_number(input, location, length) {
-        part_numbers.push(PartNumber {
-            value: number_buffer.parse::<SolutionType>().unwrap(),
-            location,
-            length,
-        });
+    fn adjacent_numbers(&self, symbol_index: usize) -> Vec<&PartNumber> {
+        self.symbol_numbers[symbol_index]
+            .iter()
+            .map(|&number_index| &self.numbers[number_index])
+            .collect()
     }
-}
 
-fn find_parts_and_gears(input: &InputType) -> PartsAndGears {
-    let mut parts: Vec<PartNumber> = Vec::new();
-    let mut gears: Vec<Point> = Vec::new();
-    for (line_num, line) in input.iter().enumerate() {
-        let mut number_buffer = String::new();
-        for (col_num, c) in line.chars().enumerate() {
-            if c.is_ascii_digit() {
-                number_buffer.push(c);
-                continue;
-            }
-            if c == '*' {
-                gears.push(Point {
-                    line: line_num,
-                    col: col_num,
-                });
-            }
-            if !number_buffer.is_empty() {
-                add_if_part_number(input, line_num, col_num, &number_buffer, &mut parts);
-                number_buffer.clear();
-            }
-        }
-        if !number_buffer.is_empty() {
-            add_if_part_number(input, line_num, line.len(), &number_buffer, &mut parts);
-            number_buffer.clear();
-        }
+    #[cfg(test)]
+    fn adjacent_symbols(&self, number_index: usize) -> Vec<&Symbol> {
+        self.number_symbols[number_index]
+            .iter()
+            .map(|&symbol_index| &self.symbols[symbol_index])
+            .collect()
+    }
+
+    // Symbols of the given kind with exactly `count` adjacent numbers, along with those numbers.
+    fn symbols_with(&self, kind: char, count: usize) -> Vec<(&Symbol, Vec<&PartNumber>)> {
+        self.symbols
+            .iter()
+            .enumerate()
+            .filter(|(i, symbol)| symbol.kind == kind && self.symbol_numbers[*i].len() == count)
+            .map(|(i, symbol)| (symbol, self.adjacent_numbers(i)))
+            .collect()
     }
-    PartsAndGears { parts, gears }
 }
 
-fn solve_part1(part_numbers: &[PartNumber]) -> SolutionType {
-    part_numbers
+fn solve_part1(index: &SchematicIndex) -> SolutionType {
+    index
+        .part_numbers()
         .iter()
         .map(|part_number| part_number.value)
         .sum()
 }
 
-fn find_gears(parts: &PartsAndGears) -> Vec<Gear> {
-    parts
-        .gears
+// A gear is any '*' next to exactly two numbers.
+fn find_gears(index: &SchematicIndex) -> Vec<Gear> {
+    index
+        .symbols_with('*', 2)
         .iter()
-        .map(|gear| {
-            parts
-                .parts
-                .iter()
-                .filter(|part| part.is_adjacent(*gear))
-                .collect::<Vec<_>>()
-        })
-        .filter(|adjacent_parts| adjacent_parts.len() == 2)
-        .map(|adjacent_parts| Gear::new(adjacent_parts[0].value, adjacent_parts[1].value))
+        .map(|(_, numbers)| Gear::new(numbers[0].value, numbers[1].value))
         .collect()
 }
 
-fn solve_part2(parts: &PartsAndGears) -> SolutionType {
-    find_gears(parts).iter().map(|gear| gear.get_ratio()).sum()
+fn solve_part2(index: &SchematicIndex) -> SolutionType {
+    find_gears(index).iter().map(|gear| gear.get_ratio()).sum()
 }
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
-    let parts = find_parts_and_gears(&input);
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
+    let index = SchematicIndex::build(&input);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
     let part1_start = std::time::Instant::now();
-    let part1 = solve_part1(&parts.parts);
+    let part1 = solve_part1(&index);
     println!("Part 1: {} ({:?})", part1, part1_start.elapsed());
 
     let part2_start = std::time::Instant::now();
-    let part2 = solve_part2(&parts);
+    let part2 = solve_part2(&index);
     println!("Part 2: {} ({:?})", part2, part2_start.elapsed());
 }
 
@@ -207,61 +244,83 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.len(), 10);
     }
 
     #[test]
     fn test_find_part_numbers() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        let parts = find_parts_and_gears(&input);
-        let part_numbers = parts.parts;
-        assert_eq!(part_numbers.len(), 8);
-        assert_eq!(part_numbers[0].value, 467);
-        assert_eq!(part_numbers[1].value, 35);
-        assert_eq!(part_numbers[2].value, 633);
-        assert_eq!(part_numbers[3].value, 617);
-        assert_eq!(part_numbers[4].value, 592);
-        assert_eq!(part_numbers[5].value, 755);
-        assert_eq!(part_numbers[6].value, 664);
-        assert_eq!(part_numbers[7].value, 598);
-        let gears = parts.gears;
+        let input = parse_input(SAMPLE_INPUT);
+        let index = SchematicIndex::build(&input);
+        let values: Vec<SolutionType> =
+            index.part_numbers().iter().map(|part| part.value).collect();
+        assert_eq!(values, vec![467, 35, 633, 617, 592, 755, 664, 598]);
+        let gears: Vec<Point> = index
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.kind == '*')
+            .map(|symbol| symbol.location)
+            .collect();
         assert_eq!(gears.len(), 3);
         assert_eq!(gears[0], Point { line: 1, col: 3 });
         assert_eq!(gears[1], Point { line: 4, col: 3 });
         assert_eq!(gears[2], Point { line: 8, col: 5 });
     }
 
+    fn is_part_number(input: &str) -> bool {
+        let index = SchematicIndex::build(&parse_input(input));
+        !index.part_numbers().is_empty()
+    }
+
     #[test]
     fn test_is_part_number() {
-        let input_no_surrounding_chars = parse_input("123".to_string());
-        let point_0_0 = Point { line: 0, col: 0 };
+        assert_eq!(is_part_number("123"), false);
+        assert_eq!(is_part_number(".....\n.123.\n....."), false);
+        assert_eq!(is_part_number("*....\n.123.\n....."), true);
+        assert_eq!(is_part_number("....*\n.123.\n....."), true);
+        assert_eq!(is_part_number(".....\n.123.\n*...."), true);
+        assert_eq!(is_part_number(".....\n.123.\n....*"), true);
+        assert_eq!(is_part_number(".....\n*123.\n....."), true);
+        assert_eq!(is_part_number(".....\n.123*\n....."), true);
+        assert_eq!(is_part_number("123\n...\n..#"), false);
+    }
+
+    #[test]
+    fn test_queries() {
+        let input = parse_input(SAMPLE_INPUT);
+        let index = SchematicIndex::build(&input);
+        let isolated: Vec<SolutionType> =
+            index.isolated_numbers().iter().map(|n| n.value).collect();
+        assert_eq!(isolated, vec![114, 58]);
+        let lonely_stars = index.symbols_with('*', 1);
+        assert_eq!(lonely_stars.len(), 1);
+        assert_eq!(lonely_stars[0].0.location, Point { line: 4, col: 3 });
+        assert_eq!(lonely_stars[0].1[0].value, 617);
+        let hashes = index.symbols_with('#', 1);
+        assert_eq!(hashes[0].1[0].value, 633);
+        // 467 is the first number and touches the '*' below it.
+        let symbols = index.adjacent_symbols(0);
         assert_eq!(
-            is_part_number(&input_no_surrounding_chars, point_0_0, 3),
-            false
+            symbols,
+            vec![&Symbol {
+                kind: '*',
+                location: Point { line: 1, col: 3 }
+            }]
         );
+    }
 
-        let input_no_special_chars = parse_input(".....\n.123.\n.....".to_string());
-        let point_1_1 = Point { line: 1, col: 1 };
-        assert_eq!(is_part_number(&input_no_special_chars, point_1_1, 3), false);
-
-        let input_above_left = parse_input("*....\n.123.\n.....".to_string());
-        assert_eq!(is_part_number(&input_above_left, point_1_1, 3), true);
-
-        let input_above_right = parse_input("....*\n.123.\n.....".to_string());
-        assert_eq!(is_part_number(&input_above_right, point_1_1, 3), true);
-
-        let input_below_left = parse_input(".....\n.123.\n*....".to_string());
-        assert_eq!(is_part_number(&input_below_left, point_1_1, 3), true);
-
-        let input_below_right = parse_input(".....\n.123.\n....*".to_string());
-        assert_eq!(is_part_number(&input_below_right, point_1_1, 3), true);
-
-        let input_left = parse_input(".....\n*123.\n.....".to_string());
-        assert_eq!(is_part_number(&input_left, point_1_1, 3), true);
-
-        let input_right = parse_input(".....\n.123*\n.....".to_string());
-        assert_eq!(is_part_number(&input_right, point_1_1, 3), true);
+    #[test]
+    fn test_index_matches_is_adjacent() {
+        let input = parse_input(SAMPLE_INPUT);
+        let index = SchematicIndex::build(&input);
+        for (symbol_index, symbol) in index.symbols.iter().enumerate() {
+            let expected: Vec<&PartNumber> = index
+                .numbers
+                .iter()
+                .filter(|number| number.is_adjacent(symbol.location))
+                .collect();
+            assert_eq!(index.adjacent_numbers(symbol_index), expected);
+        }
     }
 
     #[test]
@@ -316,17 +375,17 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        let parts = find_parts_and_gears(&input);
-        let result = solve_part1(&parts.parts);
+        let input = parse_input(SAMPLE_INPUT);
+        let index = SchematicIndex::build(&input);
+        let result = solve_part1(&index);
         assert_eq!(result, 4361)
     }
 
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        let parts = find_parts_and_gears(&input);
-        let result = solve_part2(&parts);
+        let input = parse_input(SAMPLE_INPUT);
+        let index = SchematicIndex::build(&input);
+        let result = solve_part2(&index);
         assert_eq!(result, 467835)
     }
 }
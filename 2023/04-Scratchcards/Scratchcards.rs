@@ -1,74 +1,124 @@
 // Scratchcards
 // https://adventofcode.com/2023/day/4
 
-use std::collections::HashSet;
 use std::fs::read_to_string;
 
+#[path = "../common/parser.rs"]
+mod parser;
+use parser::{
+    delimited, lines, literal, many, map, map_result, pair, parse_all, parse_lines, preceded,
+    spaces1, unsigned, Parser,
+};
+
 type InputType = Vec<Scratchcard>;
-type SolutionType = u32;
+type SolutionType = u64;
+
+// Card numbers are below this, so a card's numbers fit in one u128.
+const NUMBER_LIMIT: usize = 128;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct NumberSet(u128);
+
+impl NumberSet {
+    fn insert(&mut self, num: u32) {
+        self.0 |= 1 << num;
+    }
+
+    fn common(&self, other: &NumberSet) -> u32 {
+        (self.0 & other.0).count_ones()
+    }
+}
 
 #[derive(Debug, PartialEq)]
 struct Scratchcard {
-    winning_nums: HashSet<u32>,
-    my_nums: HashSet<u32>,
+    winning_nums: NumberSet,
+    my_nums: NumberSet,
+    // How many of my numbers are winning numbers, worked out once when the card is made.
+    matches: u32,
 }
 
 impl Scratchcard {
-    fn new(winning_nums: HashSet<u32>, my_nums: HashSet<u32>) -> Self {
+    fn new(winning_nums: NumberSet, my_nums: NumberSet) -> Self {
         Self {
             winning_nums,
             my_nums,
+            matches: winning_nums.common(&my_nums),
         }
     }
 
-    fn matches(&self) -> u32 {
-        self.winning_nums.intersection(&self.my_nums).count() as u32
-    }
-
-    fn score(&self) -> u32 {
-        let matches = self.matches();
-        if matches == 0 {
+    fn score(&self) -> SolutionType {
+        if self.matches == 0 {
             0
         } else {
-            2u32.pow(matches - 1)
+            2u64.pow(self.matches - 1)
         }
     }
 }
 
-fn parse_line(line: &str) -> Scratchcard {
-    let nums_str = line.split_once(": ").unwrap().1;
-    let (winning_nums_str, my_nums_str) = nums_str.split_once(" | ").unwrap();
-    Scratchcard::new(parse_nums(winning_nums_str), parse_nums(my_nums_str))
+// Card   1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+fn parse_line<'a>() -> impl Parser<'a, Scratchcard> {
+    let nums = || {
+        map_result(many(preceded(spaces1(), unsigned::<u32>())), |nums| {
+            if nums.iter().any(|&num| num as usize >= NUMBER_LIMIT) {
+                return Err(format!("numbers below {}", NUMBER_LIMIT));
+            }
+            Ok(nums.into_iter().fold(NumberSet::default(), |mut set, num| {
+                set.insert(num);
+                set
+            }))
+        })
+    };
+    let card_number = delimited(
+        pair(literal("Card"), spaces1()),
+        unsigned::<u32>(),
+        literal(":"),
+    );
+    let winning_nums = preceded(card_number, nums());
+    let my_nums = preceded(pair(spaces1(), literal("|")), nums());
+    map(pair(winning_nums, my_nums), |(winning_nums, my_nums)| {
+        Scratchcard::new(winning_nums, my_nums)
+    })
 }
 
-fn parse_nums(nums_str: &str) -> HashSet<u32> {
-    nums_str
-        .split_whitespace()
-        .map(|s| s.parse::<u32>().unwrap())
-        .collect()
+fn parse_input(input_str: &str) -> InputType {
+    parse_all(lines(parse_line()), input_str).unwrap_or_else(|error| panic!("{}", error))
 }
 
-fn parse_input(input_str: String) -> InputType {
-    input_str.lines().map(parse_line).collect()
+// Counts the cards held after every card has won its copies, taking each card's matches
+// in order as they arrive. Copies only go to the next `matches` cards, which is never
+// more than NUMBER_LIMIT, so the copies still to come live in a ring buffer of that size.
+fn count_cards(matches: impl IntoIterator<Item = u32>) -> SolutionType {
+    let mut pending: [SolutionType; NUMBER_LIMIT] = [0; NUMBER_LIMIT];
+    let mut total = 0;
+    for (index, matches) in matches.into_iter().enumerate() {
+        let slot = index % NUMBER_LIMIT;
+        let copies = 1 + pending[slot];
+        pending[slot] = 0;
+        total += copies;
+        for offset in 1..=matches as usize {
+            pending[(slot + offset) % NUMBER_LIMIT] += copies;
+        }
+    }
+    total
 }
 
 fn solve_part1(input: &InputType) -> SolutionType {
     input.iter().map(|card| card.score()).sum()
 }
 
-fn solve_part2(input: &InputType) -> SolutionType {
-    let mut card_counts = vec![1; input.len()];
-    for (i, card) in input.iter().enumerate() {
-        for j in i + 1..i + 1 + card.matches() as usize {
-            card_counts[j] += card_counts[i];
-        }
-    }
-    card_counts.iter().sum()
+// Works straight from the text, so each card is counted as soon as its line is parsed
+// and the cards are never all held at once.
+fn solve_part2(input_str: &str) -> SolutionType {
+    count_cards(
+        parse_lines(parse_line(), input_str)
+            .map(|card| card.unwrap_or_else(|error| panic!("{}", error)).matches),
+    )
 }
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
     let part1_start = std::time::Instant::now();
@@ -76,7 +126,7 @@ fn main() {
     println!("Part 1: {} ({:?})", part1, part1_start.elapsed());
 
     let part2_start = std::time::Instant::now();
-    let part2 = solve_part2(&input);
+    let part2 = solve_part2(&input_str);
     println!("Part 2: {} ({:?})", part2, part2_start.elapsed());
 }
 
@@ -88,28 +138,95 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.len(), 6);
+        let set = |nums: &[u32]| {
+            let mut set = NumberSet::default();
+            nums.iter().for_each(|&num| set.insert(num));
+            set
+        };
         assert_eq!(
             input[0],
             Scratchcard {
-                winning_nums: [41, 48, 83, 86, 17].iter().copied().collect(),
-                my_nums: [83, 86, 6, 31, 17, 9, 48, 53].iter().copied().collect(),
+                winning_nums: set(&[41, 48, 83, 86, 17]),
+                my_nums: set(&[83, 86, 6, 31, 17, 9, 48, 53]),
+                matches: 4,
             }
         );
+        let matches: Vec<u32> = input.iter().map(|card| card.matches).collect();
+        assert_eq!(matches, vec![4, 2, 2, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let input = "Card 1: 41 48 | 83\nCard 2: 13 x2 | 61";
+        let error = parse_all(lines(parse_line()), input).unwrap_err();
+        assert_eq!((error.line, error.column), (2, 12));
+        assert_eq!(error.expected, "\"|\"");
+        let error = parse_all(parse_line(), "Card 1: 41 128 | 83").unwrap_err();
+        assert_eq!(error.column, 8);
+        assert_eq!(error.expected, "numbers below 128");
     }
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 13);
     }
 
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        let result = solve_part2(&input);
+        let result = solve_part2(SAMPLE_INPUT);
         assert_eq!(result, 30);
     }
+
+    #[test]
+    #[should_panic(expected = "line 2, column 12")]
+    fn test_part2_parse_error() {
+        solve_part2("Card 1: 41 48 | 83\nCard 2: 13 x2 | 61");
+    }
+
+    #[test]
+    fn test_count_cards_beyond_u32() {
+        // Every card wins a copy of each card after it, so each card has twice as many
+        // copies as the one before and the total is far more than a u32 can hold.
+        let matches: Vec<u32> = (0..41).rev().collect();
+        assert_eq!(count_cards(matches.iter().copied()), (1 << 41) - 1);
+        assert_eq!(count_cards_reference(&matches), (1 << 41) - 1);
+    }
+
+    // The straightforward count, with a slot for every card.
+    fn count_cards_reference(matches: &[u32]) -> SolutionType {
+        let mut card_counts = vec![1; matches.len()];
+        for (i, &card_matches) in matches.iter().enumerate() {
+            for j in i + 1..i + 1 + card_matches as usize {
+                card_counts[j] += card_counts[i];
+            }
+        }
+        card_counts.iter().sum()
+    }
+
+    #[test]
+    fn test_count_cards() {
+        // Long runs that wrap around the ring buffer many times, including cards that win
+        // copies of the full NUMBER_LIMIT cards after them.
+        let count = 5000;
+        let matches: Vec<u32> = (0..count)
+            .map(|i| {
+                let matches = match i % 300 {
+                    0 => NUMBER_LIMIT,
+                    n if n % 7 == 0 => 3,
+                    _ => 0,
+                };
+                matches.min(count - 1 - i) as u32
+            })
+            .collect();
+        assert_eq!(
+            count_cards(matches.iter().copied()),
+            count_cards_reference(&matches)
+        );
+        assert_eq!(count_cards(vec![0; 1000]), 1000);
+        assert_eq!(count_cards(Vec::new()), 0);
+    }
 }
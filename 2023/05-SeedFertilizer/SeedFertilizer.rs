@@ -1,12 +1,16 @@
 // SeedFertilizer
 // https://adventofcode.com/2023/day/5
 
-use std::cmp::max;
-use std::cmp::min;
 use std::fs::read_to_string;
-use std::io::{self, Write};
 use std::ops::Range;
-use std::panic;
+
+#[path = "../common/range_set.rs"]
+mod range_set;
+use range_set::RangeSet;
+
+#[path = "../common/sections.rs"]
+mod sections;
+use sections::{section_lines, sections};
 
 type InputType = SeedLocation;
 type SolutionType = i64;
@@ -38,37 +42,42 @@ impl CategoryMap {
         *key
     }
 
-    // Returns an intersection in the the current range mapped back to the source range.
-    fn get_intersection(
-        &self,
-        range: &Range<SolutionType>,
-        source_offset: SolutionType,
-    ) -> (Range<SolutionType>, SolutionType) {
+    // Splits a range of keys into pieces that are each mapped by a single offset.
+    // Any part of the range not covered by an entry is mapped by identity.
+    fn split_range(&self, range: &Range<SolutionType>) -> Vec<CategoryEntry> {
+        let mut pieces = Vec::new();
+        let mut unmapped = RangeSet::from(range.clone());
         for entry in &self.entries {
-            let start = max(entry.range.start, range.start);
-            let end = min(entry.range.end, range.end);
-            if start < end {
-                return (start - source_offset..end - source_offset, entry.offset);
-            }
+            let mapped = unmapped.intersection(&RangeSet::from(entry.range.clone()));
+            pieces.extend(mapped.iter().map(|range| CategoryEntry {
+                range: range.clone(),
+                offset: entry.offset,
+            }));
+            unmapped = unmapped.difference(&mapped);
         }
-        panic!("No intersection found");
+        pieces.extend(unmapped.iter().map(|range| CategoryEntry {
+            range: range.clone(),
+            offset: 0,
+        }));
+        pieces
     }
 
     fn insert(&mut self, value_start: SolutionType, key_start: SolutionType, count: SolutionType) {
-        self.entries.push(CategoryEntry {
-            range: (key_start..key_start + count),
-            offset: value_start - key_start,
-        });
+        self.insert_range(key_start..key_start + count, value_start - key_start);
     }
 
+    // Entries are kept sorted by the start of their range.
     fn insert_range(&mut self, range: Range<SolutionType>, offset: SolutionType) {
-        self.entries.push(CategoryEntry { range, offset });
+        let index = self
+            .entries
+            .partition_point(|entry| entry.range.start < range.start);
+        self.entries.insert(index, CategoryEntry { range, offset });
     }
 
-    // Fill in any gaps in the map with identity mappings.
+    // Fill in any gaps in the map with identity mappings. Where entries overlap, the one
+    // that starts first keeps the overlapping keys, matching the lookup order of `get`.
     fn normalize(&mut self) {
-        self.entries
-            .sort_by(|a, b| a.range.start.cmp(&b.range.start));
+        self.entries.sort_by_key(|entry| entry.range.start);
         let mut next_start = 0;
         let mut new_entries = Vec::new();
         for entry in self.entries.iter() {
@@ -78,7 +87,14 @@ impl CategoryMap {
                     offset: 0,
                 });
             }
-            next_start = entry.range.end;
+            let start = entry.range.start.max(next_start);
+            if start < entry.range.end {
+                new_entries.push(CategoryEntry {
+                    range: (start..entry.range.end),
+                    offset: entry.offset,
+                });
+                next_start = entry.range.end;
+            }
         }
         if next_start < SolutionType::MAX {
             new_entries.push(CategoryEntry {
@@ -86,9 +102,61 @@ impl CategoryMap {
                 offset: 0,
             });
         }
-        self.entries.extend(new_entries);
-        self.entries
-            .sort_by(|a, b| a.range.start.cmp(&b.range.start));
+        self.entries = new_entries;
+    }
+
+    fn normalized(&self) -> CategoryMap {
+        let mut map = self.clone();
+        map.normalize();
+        map
+    }
+
+    // Create a new map equivalent to applying this map and then the next map.
+    fn compose(&self, next: &CategoryMap) -> CategoryMap {
+        let mut map = CategoryMap::new();
+        for entry in &self.normalized().entries {
+            let value_range = entry.range.start + entry.offset..entry.range.end + entry.offset;
+            for piece in next.split_range(&value_range) {
+                map.insert_range(
+                    piece.range.start - entry.offset..piece.range.end - entry.offset,
+                    entry.offset + piece.offset,
+                );
+            }
+        }
+        map
+    }
+
+    // Maps a set of keys to the set of values they map to.
+    fn map_ranges(&self, keys: &RangeSet<SolutionType>) -> RangeSet<SolutionType> {
+        keys.iter()
+            .flat_map(|range| self.split_range(range))
+            .map(|piece| piece.range.start + piece.offset..piece.range.end + piece.offset)
+            .collect()
+    }
+
+    // Returns the map from values back to keys, or None if two keys map to the same value.
+    fn invert(&self) -> Option<CategoryMap> {
+        let mut inverse = CategoryMap::new();
+        for entry in &self.normalized().entries {
+            inverse.insert_range(
+                entry.range.start + entry.offset..entry.range.end + entry.offset,
+                -entry.offset,
+            );
+        }
+        let overlaps = inverse
+            .entries
+            .windows(2)
+            .any(|pair| pair[0].range.end > pair[1].range.start);
+        if overlaps {
+            None
+        } else {
+            Some(inverse)
+        }
+    }
+
+    // Returns the minimum value for any key in the set.
+    fn min_value(&self, keys: &RangeSet<SolutionType>) -> Option<SolutionType> {
+        self.map_ranges(keys).span().map(|range| range.start)
     }
 }
 
@@ -138,9 +206,9 @@ fn parse_seeds(str: &str) -> Vec<SolutionType> {
         .collect()
 }
 
-fn parse_category_map(lines: &[String]) -> CategoryMap {
+fn parse_category_map(section: &str) -> CategoryMap {
     let mut map = CategoryMap::new();
-    for line in lines.iter().skip(1) {
+    for line in section_lines(section).skip(1) {
         let mut values = line.split(' ');
         let value_start = values.next().unwrap().parse::<SolutionType>().unwrap();
         let key_start = values.next().unwrap().parse::<SolutionType>().unwrap();
@@ -151,31 +219,18 @@ fn parse_category_map(lines: &[String]) -> CategoryMap {
     map
 }
 
-fn parse_string_groups(input_str: String) -> Vec<Vec<String>> {
-    let mut groups = Vec::new();
-    let mut group = Vec::new();
-    for line in input_str.lines() {
-        if line.is_empty() {
-            groups.push(group);
-            group = Vec::new();
-        } else {
-            group.push(line.to_string());
-        }
-    }
-    groups.push(group);
-    groups
-}
-
-fn parse_input(input_str: String) -> InputType {
-    let groups = parse_string_groups(input_str);
-    let seeds = parse_seeds(&groups[0][0]);
-    let seed_to_soil = parse_category_map(&groups[1]);
-    let soil_to_fertilizer = parse_category_map(&groups[2]);
-    let fertilzer_to_water = parse_category_map(&groups[3]);
-    let water_to_light = parse_category_map(&groups[4]);
-    let light_to_temperature = parse_category_map(&groups[5]);
-    let temperature_to_humidity = parse_category_map(&groups[6]);
-    let humidity_to_location = parse_category_map(&groups[7]);
+fn parse_input(input_str: &str) -> InputType {
+    let mut sections = sections(input_str);
+    let seeds = parse_seeds(sections.next().expect("Input has no seeds"));
+    let mut maps = sections.map(parse_category_map);
+    let mut next_map = || maps.next().expect("Input is missing a category map");
+    let seed_to_soil = next_map();
+    let soil_to_fertilizer = next_map();
+    let fertilzer_to_water = next_map();
+    let water_to_light = next_map();
+    let light_to_temperature = next_map();
+    let temperature_to_humidity = next_map();
+    let humidity_to_location = next_map();
     SeedLocation {
         seeds,
         seed_to_soil,
@@ -197,70 +252,35 @@ fn solve_part1(input: &InputType) -> SolutionType {
         .unwrap()
 }
 
-// Create a new map that maps from the source map to the target map.
-fn collapse_maps(source: CategoryMap, target: CategoryMap) -> CategoryMap {
-    let mut map = CategoryMap::new();
-    for source_entry in source.entries {
-        let mut next_range = source_entry.range;
-        loop {
-            let target_range =
-                next_range.start + source_entry.offset..next_range.end + source_entry.offset;
-            let (intersection, target_offset) =
-                target.get_intersection(&target_range, source_entry.offset);
-            map.insert_range(intersection.clone(), source_entry.offset + target_offset);
-            if intersection.end == next_range.end {
-                break;
-            }
-            next_range = intersection.end..next_range.end;
-        }
-    }
-    map
+fn get_seed_ranges(seeds: &[SolutionType]) -> RangeSet<SolutionType> {
+    seeds
+        .chunks(2)
+        .map(|pair| pair[0]..pair[0] + pair[1])
+        .collect()
 }
 
-fn get_seed_ranges(seeds: &[SolutionType]) -> Vec<Range<SolutionType>> {
-    let mut ranges = Vec::new();
-    let mut next_seed = seeds.iter();
-    while let Some(seed) = next_seed.next() {
-        let count = next_seed.next();
-        let range = *seed..*seed + *count.unwrap();
-        ranges.push(range);
-    }
-    ranges
+// The map straight from seeds to locations.
+fn seed_location_map(input: &InputType) -> CategoryMap {
+    input
+        .seed_to_soil
+        .compose(&input.soil_to_fertilizer)
+        .compose(&input.fertilzer_to_water)
+        .compose(&input.water_to_light)
+        .compose(&input.light_to_temperature)
+        .compose(&input.temperature_to_humidity)
+        .compose(&input.humidity_to_location)
 }
 
 fn solve_part2(input: &InputType) -> SolutionType {
-    println!("  ... this will take a while ...");
-    let mut seed_location =
-        collapse_maps(input.seed_to_soil.clone(), input.soil_to_fertilizer.clone());
-    seed_location = collapse_maps(seed_location, input.fertilzer_to_water.clone());
-    seed_location = collapse_maps(seed_location, input.water_to_light.clone());
-    seed_location = collapse_maps(seed_location, input.light_to_temperature.clone());
-    seed_location = collapse_maps(seed_location, input.temperature_to_humidity.clone());
-    seed_location = collapse_maps(seed_location, input.humidity_to_location.clone());
-
-    let mut min_location = SolutionType::MAX;
-    let seed_ranges = get_seed_ranges(&input.seeds);
-    let range_count = seed_ranges.len();
-    let mut i = 1;
-    for range in seed_ranges {
-        print!("  checking range {}/{}: {:?}", i, range_count, range);
-        io::stdout().flush().unwrap();
-        let range_time = std::time::Instant::now();
-        for seed in range {
-            let location = seed_location.get(&seed);
-            if location < min_location {
-                min_location = location;
-            }
-        }
-        println!(" ... took {:?}", range_time.elapsed());
-        i += 1;
-    }
-    min_location
+    seed_location_map(input)
+        .min_value(&get_seed_ranges(&input.seeds))
+        .unwrap()
 }
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
     let part1_start = std::time::Instant::now();
@@ -270,6 +290,14 @@ fn main() {
     let part2_start = std::time::Instant::now();
     let part2 = solve_part2(&input);
     println!("Part 2: {} ({:?})", part2, part2_start.elapsed());
+
+    // Follow the lowest location back to the seed planted there.
+    if let Some(location_seed) = seed_location_map(&input).invert() {
+        println!(
+            "Lowest location is reached from seed {}",
+            location_seed.get(&part2)
+        );
+    }
 }
 
 #[cfg(test)]
@@ -280,7 +308,7 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.seeds.len(), 4);
     }
 
@@ -324,7 +352,7 @@ mod tests {
     }
 
     #[test]
-    fn test_collapse_maps() {
+    fn test_compose() {
         let mut source = CategoryMap::new();
         source.insert(50, 98, 2);
         source.insert(52, 50, 48);
@@ -336,7 +364,7 @@ mod tests {
         target.insert(39, 0, 15);
         target.normalize();
 
-        let result = collapse_maps(source, target);
+        let result = source.compose(&target);
         assert_eq!(result.get(&0), 39);
         assert_eq!(result.get(&14), 53);
         assert_eq!(result.get(&15), 0);
@@ -350,16 +378,139 @@ mod tests {
         assert_eq!(result.get(&100), 100);
     }
 
+    #[test]
+    fn test_compose_unnormalized() {
+        let mut source = CategoryMap::new();
+        source.insert(50, 98, 2);
+        source.insert(52, 50, 48);
+
+        let mut target = CategoryMap::new();
+        target.insert(0, 15, 37);
+
+        let result = source.compose(&target);
+        for key in [0, 14, 15, 49, 50, 97, 98, 99, 100, 1000] {
+            assert_eq!(
+                result.get(&key),
+                target.get(&source.get(&key)),
+                "key {}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize_overlapping() {
+        let mut map = CategoryMap::new();
+        map.insert(100, 0, 20);
+        map.insert(200, 10, 20);
+        map.insert(300, 5, 5);
+        let expected: Vec<SolutionType> = (0..40).map(|key| map.get(&key)).collect();
+        map.normalize();
+        let ranges: Vec<_> = map
+            .entries
+            .iter()
+            .map(|entry| entry.range.clone())
+            .collect();
+        assert_eq!(ranges, vec![0..20, 20..30, 30..SolutionType::MAX]);
+        assert_eq!(
+            (0..40).map(|key| map.get(&key)).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_map_ranges() {
+        let mut map = CategoryMap::new();
+        map.insert(50, 98, 2);
+        map.insert(52, 50, 48);
+        map.normalize();
+
+        let map_ranges = |keys: RangeSet<SolutionType>| map.map_ranges(&keys).ranges().to_vec();
+        assert_eq!(map_ranges(RangeSet::from(79..93)), vec![81..95]);
+        assert_eq!(map_ranges(RangeSet::from(40..60)), vec![40..50, 52..62]);
+        assert_eq!(map_ranges(RangeSet::from(95..105)), vec![50..52, 97..105]);
+        assert_eq!(
+            map_ranges(vec![0..10, 5..15].into_iter().collect()),
+            vec![0..15]
+        );
+    }
+
+    #[test]
+    fn test_invert() {
+        let mut map = CategoryMap::new();
+        map.insert(50, 98, 2);
+        map.insert(52, 50, 48);
+        map.normalize();
+
+        let inverse = map.invert().unwrap();
+        for key in [0, 49, 50, 97, 98, 99, 100] {
+            assert_eq!(inverse.get(&map.get(&key)), key);
+        }
+
+        let mut overlapping = CategoryMap::new();
+        overlapping.insert(0, 10, 5);
+        overlapping.normalize();
+        assert!(overlapping.invert().is_none());
+    }
+
+    #[test]
+    fn test_invert_unnormalized() {
+        // Keys 10..15 map onto 0..5, which keys 0..5 already map to by identity.
+        let mut colliding = CategoryMap::new();
+        colliding.insert(0, 10, 5);
+        assert!(colliding.invert().is_none());
+
+        let mut swap = CategoryMap::new();
+        swap.insert(10, 0, 5);
+        swap.insert(0, 10, 5);
+        let inverse = swap.invert().unwrap();
+        for key in [0, 4, 5, 9, 10, 14, 15, 100] {
+            assert_eq!(inverse.get(&swap.get(&key)), key);
+        }
+
+        // Keys 3..5 are covered by the first entry, leaving a swap of 0..5 and 5..10.
+        let mut overlapping = CategoryMap::new();
+        overlapping.insert(5, 0, 5);
+        overlapping.insert(-2, 3, 7);
+        let inverse = overlapping.invert().unwrap();
+        for key in [0, 3, 4, 5, 9, 10] {
+            assert_eq!(inverse.get(&overlapping.get(&key)), key);
+        }
+    }
+
+    #[test]
+    fn test_min_value() {
+        let mut map = CategoryMap::new();
+        map.insert(50, 98, 2);
+        map.insert(52, 50, 48);
+        map.normalize();
+
+        assert_eq!(map.min_value(&RangeSet::from(79..93)), Some(81));
+        assert_eq!(map.min_value(&RangeSet::from(60..100)), Some(50));
+        assert_eq!(
+            map.min_value(&vec![10..20, 60..70].into_iter().collect()),
+            Some(10)
+        );
+        assert_eq!(map.min_value(&RangeSet::new()), None);
+    }
+
+    #[test]
+    fn test_get_seed_ranges() {
+        let ranges = get_seed_ranges(&[79, 14, 55, 13]);
+        assert_eq!(ranges.ranges(), &[55..68, 79..93]);
+        assert_eq!(ranges.len(), 27);
+    }
+
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 35)
     }
 
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part2(&input);
         assert_eq!(result, 46)
     }
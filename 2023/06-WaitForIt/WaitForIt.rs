@@ -21,10 +21,10 @@ fn parse_line(str: &str) -> Vec<SolutionType> {
         .collect()
 }
 
-fn parse_input(input_str: String) -> InputType {
-    let lines: Vec<String> = input_str.lines().map(str::to_string).collect();
-    let times: Vec<SolutionType> = parse_line(&lines[0]);
-    let distances: Vec<SolutionType> = parse_line(&lines[1]);
+fn parse_input(input_str: &str) -> InputType {
+    let mut lines = input_str.lines();
+    let times: Vec<SolutionType> = parse_line(lines.next().unwrap());
+    let distances: Vec<SolutionType> = parse_line(lines.next().unwrap());
     times
         .iter()
         .zip(distances.iter())
@@ -32,16 +32,22 @@ fn parse_input(input_str: String) -> InputType {
         .collect()
 }
 
-fn remove_whitespace(s: &mut str) -> String {
-    s.chars().filter(|c| !c.is_whitespace()).collect()
+// Reads the digits after the label as one number, ignoring the spaces between them.
+fn parse_kerned_number(line: &str) -> SolutionType {
+    line.split_once(": ")
+        .unwrap()
+        .1
+        .bytes()
+        .filter(u8::is_ascii_digit)
+        .fold(0, |number, digit| {
+            number * 10 + (digit - b'0') as SolutionType
+        })
 }
 
-fn parse_input_2(input_str: String) -> InputType {
-    let lines: Vec<String> = input_str.lines().map(str::to_string).collect();
-    let mut time_str = lines[0].split_once(": ").unwrap().1.to_string();
-    let time = remove_whitespace(&mut time_str).parse::<i64>().unwrap();
-    let mut distance_str = lines[1].split_once(": ").unwrap().1.to_string();
-    let distance = remove_whitespace(&mut distance_str).parse::<i64>().unwrap();
+fn parse_input_2(input_str: &str) -> InputType {
+    let mut lines = input_str.lines();
+    let time = parse_kerned_number(lines.next().unwrap());
+    let distance = parse_kerned_number(lines.next().unwrap());
     vec![Race { time, distance }]
 }
 
@@ -85,8 +91,9 @@ fn solve_part2(input: &InputType) -> SolutionType {
 }
 
 fn main() {
+    let input_str = read_to_string("input.txt").unwrap();
     let parse1_start = std::time::Instant::now();
-    let input_1 = parse_input(read_to_string("input.txt").unwrap());
+    let input_1 = parse_input(&input_str);
     println!("Parsed input 1 ({:?})", parse1_start.elapsed());
 
     let part1_start = std::time::Instant::now();
@@ -94,7 +101,7 @@ fn main() {
     println!("Part 1: {} ({:?})", part1, part1_start.elapsed());
 
     let parse2_start = std::time::Instant::now();
-    let input_2 = parse_input_2(read_to_string("input.txt").unwrap());
+    let input_2 = parse_input_2(&input_str);
     println!("Parsed input 2 ({:?})", parse2_start.elapsed());
 
     let part2_start = std::time::Instant::now();
@@ -110,7 +117,7 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.len(), 3);
         assert_eq!(input[0].time, 7);
         assert_eq!(input[0].distance, 9);
@@ -122,7 +129,7 @@ mod tests {
 
     #[test]
     fn test_parse_input_2() {
-        let input = parse_input_2(SAMPLE_INPUT.to_string());
+        let input = parse_input_2(SAMPLE_INPUT);
         assert_eq!(input.len(), 1);
         assert_eq!(input[0].time, 71530);
         assert_eq!(input[0].distance, 940200);
@@ -149,14 +156,14 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 288)
     }
 
     #[test]
     fn test_part2() {
-        let input = parse_input_2(SAMPLE_INPUT.to_string());
+        let input = parse_input_2(SAMPLE_INPUT);
         let result = solve_part2(&input);
         assert_eq!(result, 71503)
     }
@@ -4,17 +4,17 @@
 use std::collections::HashMap;
 use std::fs::read_to_string;
 
-type InputType = Vec<CamelCard>;
+type InputType<'a> = Vec<CamelCard<'a>>;
 type SolutionType = u32;
 
 #[derive(Debug, Default, Clone)]
-struct CamelCard {
-    hand: String,
+struct CamelCard<'a> {
+    hand: &'a str,
     bid: SolutionType,
 }
 
-impl CamelCard {
-    fn new(hand: String, bid: SolutionType) -> Self {
+impl<'a> CamelCard<'a> {
+    fn new(hand: &'a str, bid: SolutionType) -> Self {
         Self { hand, bid }
     }
 }
@@ -92,20 +92,20 @@ fn card_value(card: char, use_wilds: bool) -> u32 {
     }
 }
 
-fn parse_line(str: &str) -> CamelCard {
+fn parse_line(str: &str) -> CamelCard<'_> {
     let split = str.split_once(' ').unwrap();
-    let hand = split.0.to_string();
+    let hand = split.0;
     let bid = split.1.parse::<u32>().unwrap();
     CamelCard::new(hand, bid)
 }
 
-fn parse_input(input_str: String) -> InputType {
+fn parse_input(input_str: &str) -> InputType<'_> {
     input_str.lines().map(parse_line).collect()
 }
 
-fn sort_hands(hands: &InputType, use_wilds: bool) -> InputType {
+fn sort_hands<'a>(hands: &InputType<'a>, use_wilds: bool) -> InputType<'a> {
     let mut sorted = hands.clone();
-    sorted.sort_by(|a, b| hand_rank(&a.hand, use_wilds).cmp(&hand_rank(&b.hand, use_wilds)));
+    sorted.sort_by_key(|card| hand_rank(card.hand, use_wilds));
     sorted
 }
 
@@ -129,7 +129,8 @@ fn solve_part2(input: &InputType) -> SolutionType {
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
     let part1_start = std::time::Instant::now();
@@ -149,7 +150,7 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.len(), 5);
         assert_eq!(input[0].hand, "32T3K");
         assert_eq!(input[0].bid, 765);
@@ -202,7 +203,7 @@ mod tests {
 
     #[test]
     fn test_sort_hands() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let sorted = sort_hands(&input, false);
         assert_eq!(sorted[0].hand, "32T3K");
         assert_eq!(sorted[1].hand, "KTJJT");
@@ -213,14 +214,14 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 6440)
     }
 
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part2(&input);
         assert_eq!(result, 5905)
     }
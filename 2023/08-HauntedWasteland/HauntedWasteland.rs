@@ -2,46 +2,109 @@
 // https://adventofcode.com/2023/day/8
 
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::fs::read_to_string;
 
-type InputType = NetworkMap;
+#[path = "../common/numtheory.rs"]
+mod numtheory;
+use numtheory::crt_merge;
+
+#[path = "../common/parser.rs"]
+mod parser;
+use parser::{
+    blank_line, delimited, identifier, lines, literal, map, pair, parse_all, take_while1,
+    terminated, Parser,
+};
+
+type InputType<'a> = NetworkMap<'a>;
 type SolutionType = u64;
 
+type NodeId = u16;
+
+// Node names are interned so the network can be walked by index.
+// `names[id]` is the name of node `id` and `network[id]` its left and right targets.
 #[derive(Debug, PartialEq)]
-struct NetworkMap {
+struct NetworkMap<'a> {
     instructions: Vec<char>,
-    network: HashMap<String, (String, String)>,
-}
-
-fn parse_input(input_str: String) -> InputType {
-    let mut lines = input_str.lines();
-    let instructions = lines.next().unwrap().chars().collect::<Vec<_>>();
-    let network = lines
-        .skip(1)
-        .map(|line| {
-            let mut parts = line.split(" = ");
-            let node = parts.next().unwrap();
-            let mut targets_str = parts.next().unwrap();
-            targets_str = &targets_str[1..targets_str.len() - 1];
-            let targets = targets_str.split(", ").collect::<Vec<_>>();
-            (
-                node.to_string(),
-                (targets[0].to_string(), targets[1].to_string()),
-            )
-        })
+    names: Vec<&'a str>,
+    network: Vec<[NodeId; 2]>,
+}
+
+impl<'a> NetworkMap<'a> {
+    fn node(&self, name: &str) -> NodeId {
+        self.names
+            .iter()
+            .position(|&n| n == name)
+            .unwrap_or_else(|| panic!("Unknown node {}", name)) as NodeId
+    }
+
+    fn name(&self, node: NodeId) -> &'a str {
+        self.names[node as usize]
+    }
+
+    fn next(&self, node: NodeId, instruction: char) -> NodeId {
+        let [left, right] = self.network[node as usize];
+        if instruction == 'L' {
+            left
+        } else {
+            right
+        }
+    }
+
+    fn nodes_matching(&self, predicate: fn(&str) -> bool) -> Vec<bool> {
+        self.names.iter().map(|name| predicate(name)).collect()
+    }
+}
+
+// AAA = (BBB, CCC)
+fn node<'a>() -> impl Parser<'a, (&'a str, [&'a str; 2])> {
+    let targets = delimited(
+        literal("("),
+        pair(terminated(identifier(), literal(", ")), identifier()),
+        literal(")"),
+    );
+    map(
+        pair(terminated(identifier(), literal(" = ")), targets),
+        |(node, (left, right))| (node, [left, right]),
+    )
+}
+
+fn network<'a>() -> impl Parser<'a, (&'a str, Vec<(&'a str, [&'a str; 2])>)> {
+    let instructions = take_while1(|c| c == 'L' || c == 'R', "'L' or 'R'");
+    pair(terminated(instructions, blank_line()), lines(node()))
+}
+
+fn parse_input(input_str: &str) -> InputType<'_> {
+    let (instructions, nodes) =
+        parse_all(network(), input_str).unwrap_or_else(|error| panic!("{}", error));
+    let instructions = instructions.chars().collect();
+    assert!(nodes.len() <= NodeId::MAX as usize, "Too many nodes");
+
+    let ids = nodes
+        .iter()
+        .enumerate()
+        .map(|(id, (node, _))| (*node, id as NodeId))
         .collect::<HashMap<_, _>>();
+    let id = |name: &str| {
+        *ids.get(name)
+            .unwrap_or_else(|| panic!("Unknown node {}", name))
+    };
+    let network = nodes
+        .iter()
+        .map(|(_, [left, right])| [id(left), id(right)])
+        .collect();
+    let names = nodes.iter().map(|(node, _)| *node).collect();
     NetworkMap {
         instructions,
+        names,
         network,
     }
 }
 
-fn get_start_nodes(input: &InputType) -> Vec<String> {
-    input
-        .network
-        .keys()
-        .filter(|k| k.ends_with('A'))
-        .cloned()
+fn get_start_nodes(input: &InputType) -> Vec<NodeId> {
+    (0..input.names.len() as NodeId)
+        .filter(|node| input.name(*node).ends_with('A'))
         .collect::<Vec<_>>()
 }
 
@@ -53,58 +116,254 @@ fn is_end_node_2(node: &str) -> bool {
     node.ends_with('Z')
 }
 
-fn count_path(input: &InputType, start: &str, is_end_node: fn(&str) -> bool) -> SolutionType {
+fn count_path(input: &InputType, start: NodeId, is_end_node: fn(&str) -> bool) -> SolutionType {
+    let end_nodes = input.nodes_matching(is_end_node);
     let mut count = 0;
     let mut next_instruction: usize = 0;
-    let mut current = start.to_string();
-    while !is_end_node(&current) {
-        let (left, right) = input.network.get(&current).unwrap();
-        current = if input.instructions[next_instruction] == 'L' {
-            left.clone()
-        } else {
-            right.clone()
-        };
+    let mut current = start;
+    while !end_nodes[current as usize] {
+        current = input.next(current, input.instructions[next_instruction]);
         next_instruction = (next_instruction + 1) % input.instructions.len();
         count += 1;
     }
     count
 }
 
-fn lcm(x: SolutionType, y: SolutionType) -> SolutionType {
-    (x * y) / gcd(x, y)
+// Moves every ghost one step at a time until they are all on end nodes.
+// Only practical for small networks; used to check the cycle-based solver.
+#[cfg(test)]
+fn count_ghost_steps(input: &InputType, max_steps: SolutionType) -> Option<SolutionType> {
+    let end_nodes = input.nodes_matching(is_end_node_2);
+    let mut ghosts = get_start_nodes(input);
+    let mut next_instruction: usize = 0;
+    for step in 0..=max_steps {
+        if ghosts.iter().all(|ghost| end_nodes[*ghost as usize]) {
+            return Some(step);
+        }
+        let instruction = input.instructions[next_instruction];
+        for ghost in ghosts.iter_mut() {
+            *ghost = input.next(*ghost, instruction);
+        }
+        next_instruction = (next_instruction + 1) % input.instructions.len();
+    }
+    None
+}
+
+// Returns each (node, direction) edge taken by following the instructions once from the start.
+fn find_path_edges(
+    input: &InputType,
+    start: NodeId,
+    instructions: &[char],
+) -> HashSet<(NodeId, char)> {
+    let mut edges = HashSet::new();
+    let mut current = start;
+    for instruction in instructions {
+        edges.insert((current, *instruction));
+        current = input.next(current, *instruction);
+    }
+    edges
+}
+
+// Renders the network as a Graphviz digraph with edges labelled L and R.
+// If a start node and instructions are given, the edges they follow are highlighted.
+fn network_to_dot(input: &InputType, path: Option<(&str, &[char])>) -> String {
+    let path_edges = match path {
+        Some((start, instructions)) => find_path_edges(input, input.node(start), instructions),
+        None => HashSet::new(),
+    };
+    let edge_style = |node: NodeId, directions: &[char]| {
+        let taken = directions
+            .iter()
+            .any(|direction| path_edges.contains(&(node, *direction)));
+        if taken {
+            ", color=red, penwidth=2"
+        } else {
+            ""
+        }
+    };
+    let mut nodes: Vec<NodeId> = (0..input.names.len() as NodeId).collect();
+    nodes.sort_by_key(|node| input.name(*node));
+
+    let mut dot = String::from("digraph network {\n");
+    for node in &nodes {
+        let name = input.name(*node);
+        if name.ends_with('A') {
+            dot.push_str(&format!(
+                "  {} [style=filled, fillcolor=lightblue];\n",
+                name
+            ));
+        } else if name.ends_with('Z') {
+            dot.push_str(&format!(
+                "  {} [style=filled, fillcolor=palegreen];\n",
+                name
+            ));
+        }
+    }
+    for node in nodes {
+        let name = input.name(node);
+        let [left, right] = input.network[node as usize];
+        let (left_name, right_name) = (input.name(left), input.name(right));
+        if left == right {
+            let style = edge_style(node, &['L', 'R']);
+            dot.push_str(&format!(
+                "  {} -> {} [label=\"L/R\"{}];\n",
+                name, left_name, style
+            ));
+        } else {
+            let style = edge_style(node, &['L']);
+            dot.push_str(&format!(
+                "  {} -> {} [label=\"L\"{}];\n",
+                name, left_name, style
+            ));
+            let style = edge_style(node, &['R']);
+            dot.push_str(&format!(
+                "  {} -> {} [label=\"R\"{}];\n",
+                name, right_name, style
+            ));
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+// Where a ghost reaches end nodes, in steps from its start.
+// After `tail_length` steps the ghost repeats the same (node, instruction) states every
+// `cycle_length` steps, so each cycle hit `h` is also reached at `h + k * cycle_length`.
+#[derive(Debug, PartialEq)]
+struct GhostCycle {
+    tail_length: u64,
+    cycle_length: u64,
+    tail_hits: Vec<u64>,
+    cycle_hits: Vec<u64>,
+}
+
+impl GhostCycle {
+    fn is_hit(&self, step: u64) -> bool {
+        if step < self.tail_length {
+            return self.tail_hits.contains(&step);
+        }
+        let offset = self.tail_length + (step - self.tail_length) % self.cycle_length;
+        self.cycle_hits.contains(&offset)
+    }
 }
 
-fn gcd(x: SolutionType, y: SolutionType) -> SolutionType {
-    if x < y {
-        gcd_recurse(x, y)
-    } else {
-        gcd_recurse(y, x)
+fn find_ghost_cycle(input: &InputType, start: NodeId, is_end_node: fn(&str) -> bool) -> GhostCycle {
+    let end_nodes = input.nodes_matching(is_end_node);
+    let instruction_count = input.instructions.len();
+    // The step at which each (node, instruction) state was first seen.
+    let mut seen = vec![None; input.names.len() * instruction_count];
+    let mut end_hits = Vec::new();
+    let mut current = start;
+    let mut step = 0;
+    loop {
+        let instruction = step as usize % instruction_count;
+        let state = current as usize * instruction_count + instruction;
+        if let Some(tail_length) = seen[state] {
+            let (tail_hits, cycle_hits) = end_hits.into_iter().partition(|hit| *hit < tail_length);
+            return GhostCycle {
+                tail_length,
+                cycle_length: step - tail_length,
+                tail_hits,
+                cycle_hits,
+            };
+        }
+        seen[state] = Some(step);
+        if end_nodes[current as usize] {
+            end_hits.push(step);
+        }
+        current = input.next(current, input.instructions[instruction]);
+        step += 1;
     }
 }
 
-fn gcd_recurse(min: SolutionType, max: SolutionType) -> SolutionType {
-    if min == 0 {
-        max
-    } else {
-        gcd(max % min, min)
+// Returns the first step at which every ghost is on an end node, or None if that never happens.
+fn find_first_common_hit(cycles: &[GhostCycle]) -> Option<SolutionType> {
+    // Any answer before some ghost enters its cycle must be one of that ghost's tail hits.
+    let tail_answer = cycles
+        .iter()
+        .flat_map(|cycle| cycle.tail_hits.iter())
+        .filter(|step| cycles.iter().all(|cycle| cycle.is_hit(**step)))
+        .min()
+        .cloned();
+
+    // Otherwise every ghost is in its cycle, so solve the congruences for each combination
+    // of hits, adding one ghost at a time. The merged congruences all share one modulus,
+    // so there are never more of them than that modulus, however many hits each ghost has.
+    let min_step = cycles
+        .iter()
+        .map(|cycle| cycle.tail_length)
+        .max()
+        .unwrap_or(0) as i128;
+    let mut congruences = vec![(0, 1)];
+    for cycle in cycles {
+        congruences = congruences
+            .iter()
+            .flat_map(|congruence| {
+                cycle.cycle_hits.iter().filter_map(move |hit| {
+                    crt_merge(*congruence, (*hit as i128, cycle.cycle_length as i128))
+                })
+            })
+            .collect();
+        congruences.sort_unstable();
+        congruences.dedup();
+    }
+    let cycle_answer = congruences
+        .iter()
+        .map(|(remainder, modulus)| {
+            if *remainder >= min_step {
+                *remainder
+            } else {
+                remainder + (min_step - remainder + modulus - 1) / modulus * modulus
+            }
+        })
+        .min()
+        .map(|step| {
+            SolutionType::try_from(step).unwrap_or_else(|_| {
+                panic!(
+                    "The ghosts first meet after {} steps, which is too many to return",
+                    step
+                )
+            })
+        });
+
+    match (tail_answer, cycle_answer) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
     }
 }
 
 fn solve_part1(input: &InputType) -> SolutionType {
-    count_path(input, "AAA", is_end_node_1)
+    count_path(input, input.node("AAA"), is_end_node_1)
 }
 
 fn solve_part2(input: &InputType) -> SolutionType {
-    let path_lengths = get_start_nodes(input)
+    let cycles = get_start_nodes(input)
         .iter()
-        .map(|p| count_path(input, p, is_end_node_2))
+        .map(|p| find_ghost_cycle(input, *p, is_end_node_2))
         .collect::<Vec<_>>();
-    path_lengths.iter().fold(1, |acc, x| lcm(acc, *x))
+    find_first_common_hit(&cycles).expect("The ghosts never all reach end nodes together")
 }
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
+
+    // With --dot, print the network as a Graphviz digraph instead of solving. A start node
+    // can follow, optionally with instructions such as "LLR" to use instead of the input's,
+    // to highlight the edges taken by following the instructions once from that node.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--dot") {
+        let instructions: Vec<char> = match args.get(2) {
+            Some(instructions) => instructions.chars().collect(),
+            None => input.instructions.clone(),
+        };
+        let path = args
+            .get(1)
+            .map(|start| (start.as_str(), instructions.as_slice()));
+        print!("{}", network_to_dot(&input, path));
+        return;
+    }
     println!("Parsed input ({:?})", parse_start.elapsed());
 
     let part1_start = std::time::Instant::now();
@@ -125,34 +384,38 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.instructions.len(), 3);
         assert_eq!(input.network.len(), 3);
-        let aaa = input.network.get("AAA").unwrap();
-        assert_eq!(aaa.0, "BBB");
-        assert_eq!(aaa.1, "BBB");
-        let bbb = input.network.get("BBB").unwrap();
-        assert_eq!(bbb.0, "AAA");
-        assert_eq!(bbb.1, "ZZZ");
-        let zzz = input.network.get("ZZZ").unwrap();
-        assert_eq!(zzz.0, "ZZZ");
-        assert_eq!(zzz.1, "ZZZ");
+        assert_eq!(input.names, vec!["AAA", "BBB", "ZZZ"]);
+        assert_eq!(input.network[0], [1, 1]);
+        assert_eq!(input.network[1], [0, 2]);
+        assert_eq!(input.network[2], [2, 2]);
+        assert_eq!(input.node("BBB"), 1);
+        assert_eq!(input.name(2), "ZZZ");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = parse_all(network(), "LR\n\nAAA = (BBB, CCC)\nBBB = BBB, BBB)").unwrap_err();
+        assert_eq!((error.line, error.column), (4, 7));
+        assert_eq!(error.expected, "\"(\"");
+        let error = parse_all(network(), "LXR\n\nAAA = (AAA, AAA)").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 2));
     }
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 6)
     }
 
     #[test]
     fn test_get_start_nodes() {
-        let input = parse_input(SAMPLE_INPUT_2.to_string());
+        let input = parse_input(SAMPLE_INPUT_2);
         let result = get_start_nodes(&input);
-        assert_eq!(result.len(), 2);
-        assert!(result.contains(&"11A".to_string()));
-        assert!(result.contains(&"22A".to_string()));
+        assert_eq!(result, vec![input.node("11A"), input.node("22A")]);
     }
 
     #[test]
@@ -162,24 +425,146 @@ mod tests {
     }
 
     #[test]
-    fn test_lcm() {
-        assert_eq!(lcm(1, 1), 1);
-        assert_eq!(lcm(1001, 1001), 1001);
-        assert_eq!(lcm(2, 3), 6);
-        assert_eq!(lcm(3, 2), 6);
-        assert_eq!(lcm(2, 4), 4);
-        assert_eq!(lcm(4, 2), 4);
-        assert_eq!(lcm(3, 4), 12);
-        assert_eq!(lcm(4, 3), 12);
-        assert_eq!(lcm(5, 15), 15);
-        assert_eq!(lcm(15, 5), 15);
-        assert_eq!(lcm(12, 18), 36);
-        assert_eq!(lcm(18, 12), 36);
+    fn test_find_path_edges() {
+        let input = parse_input(SAMPLE_INPUT);
+        let edges = find_path_edges(&input, input.node("AAA"), &['L', 'L', 'R']);
+        assert_eq!(edges.len(), 3);
+        assert!(edges.contains(&(input.node("AAA"), 'L')));
+        assert!(edges.contains(&(input.node("BBB"), 'L')));
+        assert!(edges.contains(&(input.node("AAA"), 'R')));
+    }
+
+    #[test]
+    fn test_network_to_dot() {
+        let input = parse_input(SAMPLE_INPUT);
+        let instructions = input.instructions.repeat(2);
+        let dot = network_to_dot(&input, Some(("AAA", &instructions)));
+        assert_eq!(
+            dot,
+            "digraph network {\n\
+             \x20 AAA [style=filled, fillcolor=lightblue];\n\
+             \x20 ZZZ [style=filled, fillcolor=palegreen];\n\
+             \x20 AAA -> BBB [label=\"L/R\", color=red, penwidth=2];\n\
+             \x20 BBB -> AAA [label=\"L\", color=red, penwidth=2];\n\
+             \x20 BBB -> ZZZ [label=\"R\", color=red, penwidth=2];\n\
+             \x20 ZZZ -> ZZZ [label=\"L/R\"];\n\
+             }\n"
+        );
+        assert!(!network_to_dot(&input, None).contains("color=red"));
+    }
+
+    #[test]
+    fn test_find_ghost_cycle() {
+        let input = parse_input(SAMPLE_INPUT_2);
+        assert_eq!(
+            find_ghost_cycle(&input, input.node("11A"), is_end_node_2),
+            GhostCycle {
+                tail_length: 1,
+                cycle_length: 2,
+                tail_hits: vec![],
+                cycle_hits: vec![2],
+            }
+        );
+        assert_eq!(
+            find_ghost_cycle(&input, input.node("22A"), is_end_node_2),
+            GhostCycle {
+                tail_length: 1,
+                cycle_length: 6,
+                tail_hits: vec![],
+                cycle_hits: vec![3, 6],
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_first_common_hit() {
+        // One ghost only hits during its tail, the other only in its cycle.
+        let tail_only = GhostCycle {
+            tail_length: 10,
+            cycle_length: 4,
+            tail_hits: vec![5, 7],
+            cycle_hits: vec![],
+        };
+        let cycle_only = GhostCycle {
+            tail_length: 2,
+            cycle_length: 5,
+            tail_hits: vec![],
+            cycle_hits: vec![2],
+        };
+        assert_eq!(find_first_common_hit(&[tail_only, cycle_only]), Some(7));
+
+        // Hits that are not aligned with the cycle start never coincide.
+        let even = GhostCycle {
+            tail_length: 0,
+            cycle_length: 2,
+            tail_hits: vec![],
+            cycle_hits: vec![0],
+        };
+        let odd = GhostCycle {
+            tail_length: 0,
+            cycle_length: 4,
+            tail_hits: vec![],
+            cycle_hits: vec![1, 3],
+        };
+        assert_eq!(find_first_common_hit(&[even, odd]), None);
+
+        // Answers must wait until every ghost has entered its cycle.
+        let late = GhostCycle {
+            tail_length: 7,
+            cycle_length: 3,
+            tail_hits: vec![],
+            cycle_hits: vec![8],
+        };
+        let every_step = GhostCycle {
+            tail_length: 0,
+            cycle_length: 1,
+            tail_hits: vec![],
+            cycle_hits: vec![0],
+        };
+        assert_eq!(find_first_common_hit(&[late, every_step]), Some(8));
+
+        // Ghosts that hit on most steps give many combinations of hits, but only a few
+        // distinct remainders.
+        let busy: Vec<GhostCycle> = (0..20)
+            .map(|_| GhostCycle {
+                tail_length: 0,
+                cycle_length: 6,
+                tail_hits: vec![],
+                cycle_hits: vec![1, 2, 3, 4, 5],
+            })
+            .collect();
+        assert_eq!(find_first_common_hit(&busy), Some(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "too many to return")]
+    fn test_find_first_common_hit_overflow() {
+        // The ghosts first meet after (2^40 + 1) * 2^39 steps, which is more than 2^64.
+        let first = GhostCycle {
+            tail_length: 0,
+            cycle_length: (1 << 40) + 1,
+            tail_hits: vec![],
+            cycle_hits: vec![0],
+        };
+        let second = GhostCycle {
+            tail_length: 0,
+            cycle_length: 1 << 40,
+            tail_hits: vec![],
+            cycle_hits: vec![1 << 39],
+        };
+        find_first_common_hit(&[first, second]);
+    }
+
+    #[test]
+    fn test_count_ghost_steps() {
+        let input = parse_input(SAMPLE_INPUT_2);
+        assert_eq!(count_ghost_steps(&input, 100), Some(6));
+        assert_eq!(count_ghost_steps(&input, 5), None);
     }
 
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT_2.to_string());
+        let input = parse_input(SAMPLE_INPUT_2);
         let result = solve_part2(&input);
         assert_eq!(result, 6)
     }
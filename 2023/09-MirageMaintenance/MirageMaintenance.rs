@@ -1,10 +1,17 @@
 // MirageMaintenance
 // https://adventofcode.com/2023/day/9
 
+use std::convert::TryFrom;
+use std::fmt;
 use std::fs::read_to_string;
+use std::ops::{Add, Mul};
+
+#[path = "../common/numtheory.rs"]
+mod numtheory;
+use numtheory::gcd;
 
 type InputType = Vec<Vec<SolutionType>>;
-type SolutionType = i32;
+type SolutionType = i64;
 
 fn parse_line(line: &str) -> Vec<SolutionType> {
     line.split_whitespace()
@@ -12,59 +19,217 @@ fn parse_line(line: &str) -> Vec<SolutionType> {
         .collect()
 }
 
-fn parse_input(input_str: String) -> InputType {
+fn parse_input(input_str: &str) -> InputType {
     input_str.lines().map(parse_line).collect()
 }
 
-fn get_next_sequence(sequence: &Vec<SolutionType>) -> Vec<SolutionType> {
-    let mut next = Vec::new();
-    for i in 1..sequence.len() {
-        next.push(sequence[i] - sequence[i - 1]);
+// An exact fraction kept in lowest terms with a positive denominator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    fn new(numerator: i128, denominator: i128) -> Self {
+        assert!(denominator != 0, "Zero denominator");
+        let divisor = gcd(numerator, denominator) * denominator.signum();
+        Self {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        }
+    }
+
+    fn integer(value: i128) -> Self {
+        Self::new(value, 1)
+    }
+}
+
+impl Add for Rational {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let overflow = || -> i128 { panic!("Overflow adding {} and {}", self, other) };
+        let denominator = (self.denominator / gcd(self.denominator, other.denominator))
+            .checked_mul(other.denominator)
+            .unwrap_or_else(overflow);
+        let numerator = self
+            .numerator
+            .checked_mul(denominator / self.denominator)
+            .and_then(|a| {
+                other
+                    .numerator
+                    .checked_mul(denominator / other.denominator)
+                    .and_then(|b| a.checked_add(b))
+            })
+            .unwrap_or_else(overflow);
+        Self::new(numerator, denominator)
+    }
+}
+
+impl Mul for Rational {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let overflow = || -> i128 { panic!("Overflow multiplying {} and {}", self, other) };
+        let a = gcd(self.numerator, other.denominator).max(1);
+        let b = gcd(other.numerator, self.denominator).max(1);
+        Self::new(
+            (self.numerator / a)
+                .checked_mul(other.numerator / b)
+                .unwrap_or_else(overflow),
+            (self.denominator / b)
+                .checked_mul(other.denominator / a)
+                .unwrap_or_else(overflow),
+        )
     }
-    next
 }
 
-fn get_all_sequences(sequence: &[SolutionType]) -> Vec<Vec<SolutionType>> {
-    let mut sequences = Vec::new();
-    sequences.push(sequence.to_owned());
-    let mut next = sequence.to_owned();
-    loop {
-        next = get_next_sequence(&next);
-        sequences.push(next.clone());
-        if next.iter().all(|&x| x == 0) {
-            break;
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
         }
     }
-    sequences
 }
 
-fn get_next_value(sequence: &Vec<SolutionType>) -> SolutionType {
-    get_all_sequences(sequence)
-        .iter()
-        .rev()
-        .fold(0, |acc, x| acc + x[x.len() - 1])
+// The minimal-degree polynomial through a sequence, with the first value at x = 0.
+// Stored in Newton form: p(x) = sum of differences[k] * C(x, k),
+// where differences[k] is the k-th forward difference at the start of the sequence.
+#[derive(Debug, PartialEq)]
+struct Polynomial {
+    differences: Vec<i128>,
+    len: usize,
+}
+
+impl Polynomial {
+    fn fit(sequence: &[SolutionType]) -> Self {
+        // Each level of differences overwrites the previous one in the same buffer.
+        let mut row: Vec<i128> = sequence.iter().map(|v| *v as i128).collect();
+        let mut differences = Vec::new();
+        let mut len = row.len();
+        while len > 0 && row[..len].iter().any(|v| *v != 0) {
+            differences.push(row[0]);
+            for i in 0..len - 1 {
+                row[i] = row[i + 1] - row[i];
+            }
+            len -= 1;
+        }
+        Self {
+            differences,
+            len: sequence.len(),
+        }
+    }
+
+    // The zero polynomial is treated as having degree 0.
+    fn degree(&self) -> usize {
+        self.differences.len().max(1) - 1
+    }
+
+    fn value_at(&self, x: i128) -> i128 {
+        let mut value: i128 = 0;
+        // C(x, k) for any integer x, built up one factor at a time.
+        // Each division is exact because the product of k consecutive integers is divisible by k!.
+        let mut binomial: i128 = 1;
+        for (k, difference) in self.differences.iter().enumerate() {
+            if k > 0 {
+                binomial = binomial
+                    .checked_mul(x - k as i128 + 1)
+                    .expect("Overflow evaluating polynomial")
+                    / k as i128;
+            }
+            let term = difference
+                .checked_mul(binomial)
+                .expect("Overflow evaluating polynomial");
+            value = value
+                .checked_add(term)
+                .expect("Overflow evaluating polynomial");
+        }
+        value
+    }
+
+    // Returns the value the given number of steps after the end of the sequence.
+    fn extrapolate_forward(&self, steps: usize) -> i128 {
+        self.value_at(self.len as i128 - 1 + steps as i128)
+    }
+
+    // Returns the value the given number of steps before the start of the sequence.
+    fn extrapolate_backward(&self, steps: usize) -> i128 {
+        self.value_at(-(steps as i128))
+    }
+
+    // Returns the coefficients of x^0, x^1, ... x^degree.
+    fn coefficients(&self) -> Vec<Rational> {
+        let mut coefficients = vec![Rational::integer(0); self.degree() + 1];
+        // The falling factorial x(x - 1)...(x - k + 1) with integer coefficients.
+        let mut falling = vec![1_i128];
+        let mut factorial: i128 = 1;
+        for (k, difference) in self.differences.iter().enumerate() {
+            if k > 0 {
+                factorial = factorial
+                    .checked_mul(k as i128)
+                    .expect("Overflow computing coefficients");
+                let mut next = vec![0; falling.len() + 1];
+                for (power, coefficient) in falling.iter().enumerate() {
+                    next[power + 1] += coefficient;
+                    next[power] -= coefficient * (k as i128 - 1);
+                }
+                falling = next;
+            }
+            let scale = Rational::new(*difference, factorial);
+            for (power, coefficient) in falling.iter().enumerate() {
+                coefficients[power] = coefficients[power] + scale * Rational::integer(*coefficient);
+            }
+        }
+        coefficients
+    }
+}
+
+fn get_next_value(sequence: &[SolutionType]) -> SolutionType {
+    let value = Polynomial::fit(sequence).extrapolate_forward(1);
+    SolutionType::try_from(value).expect("extrapolated value out of range")
 }
 
 fn solve_part1(input: &InputType) -> SolutionType {
-    input.iter().map(get_next_value).sum()
+    input.iter().map(|sequence| get_next_value(sequence)).sum()
 }
 
-fn get_prev_value(sample_input: &Vec<SolutionType>) -> SolutionType {
-    get_all_sequences(sample_input)
-        .iter()
-        .rev()
-        .fold(0, |acc, x| x[0] - acc)
+fn get_prev_value(sequence: &[SolutionType]) -> SolutionType {
+    let value = Polynomial::fit(sequence).extrapolate_backward(1);
+    SolutionType::try_from(value).expect("extrapolated value out of range")
 }
 
 fn solve_part2(input: &InputType) -> SolutionType {
-    input.iter().map(get_prev_value).sum()
+    input.iter().map(|sequence| get_prev_value(sequence)).sum()
 }
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
+    // Show the polynomial behind each sequence, lowest power first.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--fit") {
+        for sequence in &input {
+            let polynomial = Polynomial::fit(sequence);
+            let coefficients: Vec<String> = polynomial
+                .coefficients()
+                .iter()
+                .map(Rational::to_string)
+                .collect();
+            println!(
+                "Degree {}: {}",
+                polynomial.degree(),
+                coefficients.join(", ")
+            );
+        }
+        return;
+    }
+
     let part1_start = std::time::Instant::now();
     let part1 = solve_part1(&input);
     println!("Part 1: {} ({:?})", part1, part1_start.elapsed());
@@ -82,7 +247,7 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.len(), 3);
         assert_eq!(input[0].len(), 6);
         assert_eq!(input[1].len(), 6);
@@ -90,37 +255,112 @@ mod tests {
     }
 
     #[test]
-    fn test_get_next_sequence() {
+    fn test_fit() {
+        assert_eq!(
+            Polynomial::fit(&[0, 3, 6, 9, 12, 15]).differences,
+            vec![0, 3]
+        );
+        assert_eq!(
+            Polynomial::fit(&[1, 3, 6, 10, 15, 21]).differences,
+            vec![1, 2, 1]
+        );
+        assert_eq!(
+            Polynomial::fit(&[10, 13, 16, 21, 30, 45]).differences,
+            vec![10, 3, 0, 2]
+        );
+        assert_eq!(Polynomial::fit(&[0, 0, 0]).differences, vec![]);
+        assert_eq!(Polynomial::fit(&[3, 3, 3, 3, 3]).degree(), 0);
+        assert_eq!(Polynomial::fit(&[1, 3, 6, 10, 15, 21]).degree(), 2);
+        assert_eq!(Polynomial::fit(&[10, 13, 16, 21, 30, 45]).degree(), 3);
+        // Too few values to find a repeating difference.
+        assert_eq!(Polynomial::fit(&[1, 5, 2]).degree(), 2);
+    }
+
+    #[test]
+    fn test_coefficients() {
+        let coefficients = |sequence: &[SolutionType]| {
+            Polynomial::fit(sequence)
+                .coefficients()
+                .iter()
+                .map(Rational::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(coefficients(&[0, 3, 6, 9, 12, 15]), vec!["0", "3"]);
+        // Triangular numbers starting at 1: (x + 1)(x + 2) / 2
         assert_eq!(
-            get_next_sequence(&vec![0, 3, 6, 9, 12, 15]),
-            vec![3, 3, 3, 3, 3]
+            coefficients(&[1, 3, 6, 10, 15, 21]),
+            vec!["1", "3/2", "1/2"]
         );
-        assert_eq!(get_next_sequence(&vec![3, 3, 3, 3, 3]), vec![0, 0, 0, 0]);
         assert_eq!(
-            get_next_sequence(&vec![1, 3, 6, 10, 15, 21]),
-            vec![2, 3, 4, 5, 6]
+            coefficients(&[10, 13, 16, 21, 30, 45]),
+            vec!["10", "11/3", "-1", "1/3"]
         );
-        assert_eq!(get_next_sequence(&vec![2, 3, 4, 5, 6]), vec![1, 1, 1, 1]);
-        assert_eq!(get_next_sequence(&vec![1, 1, 1, 1]), vec![0, 0, 0]);
+        assert_eq!(coefficients(&[0, 0, 0]), vec!["0"]);
+    }
+
+    #[test]
+    fn test_rational() {
+        let half = Rational::new(2, -4);
+        assert_eq!(half, Rational::new(-1, 2));
+        assert_eq!(half + Rational::new(5, 6), Rational::new(1, 3));
+        assert_eq!(half * Rational::new(-4, 3), Rational::new(2, 3));
+        assert_eq!((Rational::new(1, 3) + Rational::new(2, 3)).to_string(), "1");
+        // Reducing first keeps the intermediate products in range.
+        let large = Rational::new(i128::MAX, 3);
+        assert_eq!(large * Rational::new(3, i128::MAX), Rational::integer(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Overflow adding")]
+    fn test_rational_add_overflow() {
+        let _ = Rational::integer(i128::MAX) + Rational::integer(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Overflow multiplying")]
+    fn test_rational_mul_overflow() {
+        let _ = Rational::new(i128::MAX, 5) * Rational::new(7, 3);
+    }
+
+    #[test]
+    fn test_extrapolate() {
+        let polynomial = Polynomial::fit(&[10, 13, 16, 21, 30, 45]);
+        assert_eq!(polynomial.extrapolate_forward(1), 68);
+        assert_eq!(polynomial.extrapolate_backward(1), 5);
+        for (x, expected) in [10, 13, 16, 21, 30, 45].iter().enumerate() {
+            assert_eq!(polynomial.value_at(x as i128), *expected);
+        }
+        // 10 + 11x/3 - x^2 + x^3/3 at x = 1_000_005.
+        assert_eq!(
+            polynomial.extrapolate_forward(1_000_000),
+            333_337_333_352_000_045
+        );
+        assert_eq!(polynomial.extrapolate_backward(3), -19);
     }
 
     #[test]
     fn test_get_next_value() {
-        assert_eq!(get_next_value(&vec![0, 3, 6, 9, 12, 15]), 18);
-        assert_eq!(get_next_value(&vec![1, 3, 6, 10, 15, 21]), 28);
-        assert_eq!(get_next_value(&vec![10, 13, 16, 21, 30, 45]), 68);
+        assert_eq!(get_next_value(&[0, 3, 6, 9, 12, 15]), 18);
+        assert_eq!(get_next_value(&[1, 3, 6, 10, 15, 21]), 28);
+        assert_eq!(get_next_value(&[10, 13, 16, 21, 30, 45]), 68);
+    }
+
+    #[test]
+    #[should_panic(expected = "extrapolated value out of range")]
+    fn test_get_next_value_out_of_range() {
+        get_next_value(&[SolutionType::MAX - 1, SolutionType::MAX]);
     }
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 114)
     }
 
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part2(&input);
         assert_eq!(result, 2)
     }
@@ -1,221 +1,432 @@
 // PipeMaze
 // https://adventofcode.com/2023/day/10
 
-use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::fmt;
 use std::fs::read_to_string;
-use std::ops::Range;
 use std::panic;
 
 type InputType = PipeMaze;
-type MazeType = Vec<Vec<char>>;
+type MazeType = Vec<Vec<Pipe>>;
 type SolutionType = i32;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+impl Direction {
+    fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    // The neighbouring tile in this direction, if it is inside a maze of the given size.
+    fn step(self, (x, y): (usize, usize), width: usize, height: usize) -> Option<(usize, usize)> {
+        match self {
+            Direction::North if y > 0 => Some((x, y - 1)),
+            Direction::East if x + 1 < width => Some((x + 1, y)),
+            Direction::South if y + 1 < height => Some((x, y + 1)),
+            Direction::West if x > 0 => Some((x - 1, y)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pipe {
+    Vertical,
+    Horizontal,
+    NorthEast,
+    NorthWest,
+    SouthWest,
+    SouthEast,
+    Ground,
+}
+
+const PIPES: [Pipe; 6] = [
+    Pipe::Vertical,
+    Pipe::Horizontal,
+    Pipe::NorthEast,
+    Pipe::NorthWest,
+    Pipe::SouthWest,
+    Pipe::SouthEast,
+];
+
+impl Pipe {
+    fn from_char(c: char) -> Option<Pipe> {
+        match c {
+            '|' => Some(Pipe::Vertical),
+            '-' => Some(Pipe::Horizontal),
+            'L' => Some(Pipe::NorthEast),
+            'J' => Some(Pipe::NorthWest),
+            '7' => Some(Pipe::SouthWest),
+            'F' => Some(Pipe::SouthEast),
+            '.' => Some(Pipe::Ground),
+            _ => None,
+        }
+    }
+
+    // The directions the pipe leads in, none for ground.
+    fn directions(self) -> &'static [Direction] {
+        match self {
+            Pipe::Vertical => &[Direction::North, Direction::South],
+            Pipe::Horizontal => &[Direction::East, Direction::West],
+            Pipe::NorthEast => &[Direction::North, Direction::East],
+            Pipe::NorthWest => &[Direction::North, Direction::West],
+            Pipe::SouthWest => &[Direction::South, Direction::West],
+            Pipe::SouthEast => &[Direction::South, Direction::East],
+            Pipe::Ground => &[],
+        }
+    }
+
+    fn connects(self, direction: Direction) -> bool {
+        self.directions().contains(&direction)
+    }
+
+    fn is_corner(self) -> bool {
+        !matches!(self, Pipe::Vertical | Pipe::Horizontal | Pipe::Ground)
+    }
+}
+
 #[derive(Debug, PartialEq)]
 struct PipeMaze {
-    maze: Vec<Vec<char>>,
+    maze: MazeType,
     start: (usize, usize),
 }
 
-#[allow(dead_code)]
-fn print_maze(maze: &MazeType) {
-    for row in maze {
-        for c in row {
-            let tc = match c {
-                'L' => '╚',
-                'J' => '╝',
-                '7' => '╗',
-                'F' => '╔',
-                '|' => '║',
-                '-' => '═',
-                _ => *c,
-            };
-            print!("{}", tc);
-        }
-        println!();
+impl PipeMaze {
+    fn width(&self) -> usize {
+        self.maze[0].len()
+    }
+
+    fn height(&self) -> usize {
+        self.maze.len()
+    }
+
+    fn pipe(&self, (x, y): (usize, usize)) -> Pipe {
+        self.maze[y][x]
+    }
+
+    // The tiles the pipe at a tile leads to.
+    fn connections(&self, tile: (usize, usize)) -> Vec<(usize, usize)> {
+        self.pipe(tile)
+            .directions()
+            .iter()
+            .filter_map(|direction| direction.step(tile, self.width(), self.height()))
+            .collect()
     }
 }
 
-#[allow(dead_code)]
-fn print_path_lengths(maze: &Vec<Vec<i32>>) {
-    for row in maze {
-        for length in row {
-            if *length == -1 {
-                print!(". ");
-                continue;
+#[derive(Debug, PartialEq)]
+enum MazeError {
+    UnknownTile { tile: char, x: usize, y: usize },
+    RaggedRow { y: usize },
+    NoStart,
+    MultipleStarts,
+    // How many neighbouring pipes lead into the start, when it is not exactly two.
+    StartConnections(usize),
+}
+
+impl fmt::Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MazeError::UnknownTile { tile, x, y } => {
+                write!(f, "Unknown tile {:?} at ({}, {})", tile, x, y)
             }
-            print!("{} ", length);
+            MazeError::RaggedRow { y } => write!(f, "Row {} is not as wide as the first row", y),
+            MazeError::NoStart => write!(f, "No start tile S"),
+            MazeError::MultipleStarts => write!(f, "More than one start tile S"),
+            MazeError::StartConnections(count) => write!(
+                f,
+                "Start tile S must connect to exactly two pipes, but {} lead into it",
+                count
+            ),
         }
-        println!();
     }
 }
 
-fn find_start(maze: &[Vec<char>]) -> (usize, usize) {
-    for (y, row) in maze.iter().enumerate() {
-        for (x, c) in row.iter().enumerate() {
-            if *c == 'S' {
-                return (x, y);
+// The pipe under S: the one leading to exactly the neighbours whose pipes lead back into S.
+fn infer_start(maze: &[Vec<Pipe>], start: (usize, usize)) -> Result<Pipe, MazeError> {
+    let (width, height) = (maze[0].len(), maze.len());
+    let connected: Vec<Direction> = DIRECTIONS
+        .iter()
+        .copied()
+        .filter(|direction| match direction.step(start, width, height) {
+            Some((x, y)) => maze[y][x].connects(direction.opposite()),
+            None => false,
+        })
+        .collect();
+    PIPES
+        .iter()
+        .copied()
+        .find(|pipe| connected.len() == 2 && connected.iter().all(|&d| pipe.connects(d)))
+        .ok_or(MazeError::StartConnections(connected.len()))
+}
+
+fn parse_maze(input_str: &str) -> Result<PipeMaze, MazeError> {
+    let mut start = None;
+    let mut maze: MazeType = Vec::new();
+    for (y, line) in input_str.lines().enumerate() {
+        let mut row = Vec::new();
+        for (x, tile) in line.chars().enumerate() {
+            if tile == 'S' {
+                if start.is_some() {
+                    return Err(MazeError::MultipleStarts);
+                }
+                start = Some((x, y));
+                // Filled in once the neighbours are known.
+                row.push(Pipe::Ground);
+                continue;
             }
+            row.push(Pipe::from_char(tile).ok_or(MazeError::UnknownTile { tile, x, y })?);
         }
+        if y > 0 && row.len() != maze[0].len() {
+            return Err(MazeError::RaggedRow { y });
+        }
+        maze.push(row);
     }
-    panic!("No start found");
+    let start = start.ok_or(MazeError::NoStart)?;
+    maze[start.1][start.0] = infer_start(&maze, start)?;
+    Ok(PipeMaze { maze, start })
 }
 
-fn parse_input(input_str: String) -> InputType {
-    let maze: MazeType = input_str.lines().map(|s| s.chars().collect()).collect();
-    let start = find_start(&maze);
-    PipeMaze { maze, start }
+fn parse_input(input_str: &str) -> InputType {
+    parse_maze(input_str).unwrap_or_else(|error| panic!("{}", error))
 }
 
-fn connects_north(c: char) -> bool {
-    c == '|' || c == 'L' || c == 'J'
+// The tiles of the loop in the order they are visited, starting from S.
+fn trace_loop(input: &PipeMaze) -> Vec<(usize, usize)> {
+    let mut path = vec![input.start];
+    let mut previous = input.start;
+    let mut current = input.connections(input.start)[0];
+    while current != input.start {
+        path.push(current);
+        let next = input
+            .connections(current)
+            .into_iter()
+            .find(|&tile| tile != previous)
+            .expect("Loop is broken");
+        previous = current;
+        current = next;
+    }
+    path
 }
 
-fn connects_south(c: char) -> bool {
-    c == '|' || c == 'F' || c == '7'
+// The corners of the loop in order. Straight pipes lie on the edges between them.
+fn loop_vertices(input: &PipeMaze, path: &[(usize, usize)]) -> Vec<(i64, i64)> {
+    path.iter()
+        .filter(|&&tile| input.pipe(tile).is_corner())
+        .map(|&(x, y)| (x as i64, y as i64))
+        .collect()
 }
 
-fn connects_east(c: char) -> bool {
-    c == '-' || c == 'L' || c == 'F'
+// Twice the area enclosed by the polygon, by the shoelace formula.
+fn double_area(vertices: &[(i64, i64)]) -> i64 {
+    let n = vertices.len();
+    (0..n)
+        .map(|i| {
+            let (x1, y1) = vertices[i];
+            let (x2, y2) = vertices[(i + 1) % n];
+            x1 * y2 - x2 * y1
+        })
+        .sum::<i64>()
+        .abs()
 }
 
-fn connects_west(c: char) -> bool {
-    c == '-' || c == 'J' || c == '7'
+// Pick's theorem relates the area of a polygon on grid points to the points on its
+// boundary b and strictly inside it i: A = i + b / 2 - 1. The loop's tiles are the
+// boundary points, so the tiles inside the loop are i = A - b / 2 + 1.
+fn count_inside(input: &PipeMaze) -> SolutionType {
+    let path = trace_loop(input);
+    let double_area = double_area(&loop_vertices(input, &path));
+    ((double_area - path.len() as i64) / 2 + 1) as SolutionType
 }
 
-fn get_starting_connections(maze: &[Vec<char>], start: (usize, usize)) -> Vec<(usize, usize)> {
-    let mut connections = Vec::new();
-    let (x, y) = start;
-    if x > 0 && connects_east(maze[y][x - 1]) {
-        connections.push((x - 1, y));
-    }
-    if x < maze[0].len() - 1 && connects_west(maze[y][x + 1]) {
-        connections.push((x + 1, y));
-    }
-    if y > 0 && connects_south(maze[y - 1][x]) {
-        connections.push((x, y - 1));
-    }
-    if y < maze.len() - 1 && connects_north(maze[y + 1][x]) {
-        connections.push((x, y + 1));
-    }
-    connections
-}
+// The number of steps along the loop from S to each tile on it, or None for tiles off it.
+type Distances = Vec<Vec<Option<SolutionType>>>;
 
-fn find_path_length(
-    maze: &[Vec<char>],
-    start: (usize, usize),
-) -> (SolutionType, HashSet<(usize, usize)>) {
-    let mut visited = HashSet::new();
+// Finds the distances by breadth first search.
+fn find_distances(input: &PipeMaze) -> Distances {
+    let mut distances = vec![vec![None; input.width()]; input.height()];
     let mut queue = VecDeque::new();
-    visited.insert(start);
-    for connection in get_starting_connections(maze, start) {
-        queue.push_back(connection);
-    }
-    while let Some((x, y)) = queue.pop_front() {
-        if visited.contains(&(x, y)) {
+    queue.push_back((input.start, 0));
+    while let Some(((x, y), distance)) = queue.pop_front() {
+        if distances[y][x].is_some() {
             continue;
         }
-        visited.insert((x, y));
-        let cell = maze[y][x];
-        if connects_east(cell) {
-            queue.push_back((x + 1, y));
-        }
-        if connects_west(cell) {
-            queue.push_back((x - 1, y));
-        }
-        if connects_south(cell) {
-            queue.push_back((x, y + 1));
-        }
-        if connects_north(cell) {
-            queue.push_back((x, y - 1));
+        distances[y][x] = Some(distance);
+        for tile in input.connections((x, y)) {
+            queue.push_back((tile, distance + 1));
         }
     }
-    ((visited.len() / 2) as SolutionType, visited)
+    distances
 }
 
-fn solve_part1(input: &InputType) -> SolutionType {
-    find_path_length(&input.maze, input.start).0
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tile {
+    Loop,
+    Inside,
+    Outside,
 }
 
-fn find_bounding_rect(path: &HashSet<(usize, usize)>) -> (Range<usize>, Range<usize>) {
-    let (minx, maxx, miny, maxy) = path.iter().fold(
-        (usize::MAX, 0, usize::MAX, 0),
-        |(minx, maxx, miny, maxy), (x, y)| (minx.min(*x), maxx.max(*x), miny.min(*y), maxy.max(*y)),
-    );
-    (minx..maxx + 1, miny..maxy + 1)
+// Sorts every tile by where it is relative to the loop. Scanning a row from the left,
+// each loop pipe leading north crosses the loop's boundary once.
+fn classify_tiles(input: &PipeMaze, distances: &Distances) -> Vec<Vec<Tile>> {
+    (0..input.height())
+        .map(|y| {
+            let mut inside = false;
+            (0..input.width())
+                .map(|x| {
+                    if distances[y][x].is_none() {
+                        return if inside { Tile::Inside } else { Tile::Outside };
+                    }
+                    if input.pipe((x, y)).connects(Direction::North) {
+                        inside = !inside;
+                    }
+                    Tile::Loop
+                })
+                .collect()
+        })
+        .collect()
 }
 
-fn find_points_inside(
-    path: HashSet<(usize, usize)>,
-    row: Vec<char>,
-    xrange: Range<usize>,
-    y: usize,
-) -> SolutionType {
-    // Note that it's possible for the 'S' to be a corner in some inputs but it wasn't in mine.
-    // If it is, it needs to be added to these sets.
-    // Ideally I should have replaced it when parsing the input.
-    let walls: HashSet<char> = ['L', 'J', '7', 'F', '|'].iter().cloned().collect();
-    let corners: HashSet<char> = ['L', 'J', '7', 'F'].iter().cloned().collect();
-
-    let mut count = 0;
-    let mut inside = false;
-    let mut prev_corner: Option<char> = None;
-    for x in xrange {
-        if path.contains(&(x, y)) {
-            let cell = row[x];
-            if walls.contains(&cell) {
-                if corners.contains(&cell) {
-                    if let Some(prev) = prev_corner {
-                        // These corners extend the vertical edge we've already accounted for.
-                        if (prev == 'L' && cell == '7') || (prev == 'F' && cell == 'J') {
-                            prev_corner = None;
-                            continue;
-                        }
-                    }
-                    prev_corner = Some(cell);
-                }
-                inside = !inside;
-            }
-            continue;
-        }
-        if inside {
-            count += 1;
+fn box_char(pipe: Pipe) -> char {
+    match pipe {
+        Pipe::Vertical => '│',
+        Pipe::Horizontal => '─',
+        Pipe::NorthEast => '└',
+        Pipe::NorthWest => '┘',
+        Pipe::SouthWest => '┐',
+        Pipe::SouthEast => '┌',
+        Pipe::Ground => ' ',
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Style {
+    // Loop in box drawing characters, with tiles inside marked I and outside O.
+    Plain,
+    // Tiles inside and outside the loop shaded in different colours.
+    Color,
+    // As Color, with the loop coloured from blue to red by distance from S.
+    Heatmap,
+}
+
+impl Style {
+    fn from_name(name: &str) -> Option<Style> {
+        match name {
+            "plain" => Some(Style::Plain),
+            "color" => Some(Style::Color),
+            "heatmap" => Some(Style::Heatmap),
+            _ => None,
         }
     }
-    count
 }
 
-fn replace_non_path_cells(maze: &MazeType, path: &HashSet<(usize, usize)>) -> MazeType {
-    let mut new_maze = maze.clone();
-    for (y, row) in new_maze.iter_mut().enumerate() {
-        for (x, c) in row.iter_mut().enumerate() {
-            if !path.contains(&(x, y)) {
-                *c = '.';
+const RESET: &str = "\x1b[0m";
+const INSIDE_COLOR: &str = "\x1b[30;42m";
+const OUTSIDE_COLOR: &str = "\x1b[37;44m";
+const LOOP_COLOR: &str = "\x1b[1;97m";
+// ANSI 256-colour codes running from blue through green and yellow to red.
+const HEATMAP_COLORS: [u8; 16] = [
+    21, 27, 33, 39, 45, 51, 49, 47, 46, 118, 190, 226, 220, 214, 208, 196,
+];
+
+fn heatmap_color(distance: SolutionType, max_distance: SolutionType) -> String {
+    let index = distance as usize * (HEATMAP_COLORS.len() - 1) / max_distance.max(1) as usize;
+    format!("\x1b[1;38;5;{}m", HEATMAP_COLORS[index])
+}
+
+// Draws the maze with the loop in box drawing characters, to see what is inside it.
+// Pipes that are not part of the loop are drawn too, so stray pipes are easy to spot.
+fn render_maze(input: &PipeMaze, distances: &Distances, style: Style) -> String {
+    let tiles = classify_tiles(input, distances);
+    let max_distance = distances
+        .iter()
+        .flatten()
+        .flatten()
+        .copied()
+        .max()
+        .unwrap_or(0);
+    let mut result = String::new();
+    for (y, row) in tiles.iter().enumerate() {
+        for (x, &tile) in row.iter().enumerate() {
+            let pipe = input.pipe((x, y));
+            let c = match (style, tile, pipe) {
+                (Style::Plain, Tile::Inside, _) => 'I',
+                (Style::Plain, Tile::Outside, _) => 'O',
+                (_, _, Pipe::Ground) => '·',
+                _ => box_char(pipe),
+            };
+            let color = match (style, tile) {
+                (Style::Plain, _) => String::new(),
+                (_, Tile::Inside) => INSIDE_COLOR.to_string(),
+                (_, Tile::Outside) => OUTSIDE_COLOR.to_string(),
+                (Style::Heatmap, Tile::Loop) => {
+                    heatmap_color(distances[y][x].unwrap(), max_distance)
+                }
+                (_, Tile::Loop) => LOOP_COLOR.to_string(),
+            };
+            if style == Style::Plain {
+                result.push(c);
+            } else {
+                result.push_str(&format!("{}{}{}", color, c, RESET));
             }
         }
+        result.push('\n');
     }
-    new_maze
+    result
 }
 
-fn solve_part2(input: &InputType) -> SolutionType {
-    let (_, path) = find_path_length(&input.maze, input.start);
-    let (range_x, range_y) = find_bounding_rect(&path);
+fn print_maze(input: &PipeMaze, style: Style) {
+    print!("{}", render_maze(input, &find_distances(input), style));
+}
 
-    // Not strictly necessary but makes it easier to debug.
-    let new_maze = replace_non_path_cells(&input.maze, &path);
-    // print_maze(&new_maze);
+fn solve_part1(input: &InputType) -> SolutionType {
+    find_distances(input)
+        .iter()
+        .flatten()
+        .flatten()
+        .copied()
+        .max()
+        .unwrap()
+}
 
-    range_y
-        .clone()
-        .map(|y| find_points_inside(path.clone(), new_maze[y].clone(), range_x.clone(), y))
-        .sum()
+fn solve_part2(input: &InputType) -> SolutionType {
+    count_inside(input)
 }
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
+    // With --render [plain|color|heatmap], draw the maze instead of solving.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--render") {
+        let name = args.get(1).map_or("color", String::as_str);
+        let style = Style::from_name(name)
+            .unwrap_or_else(|| panic!("Unknown style {}, expected plain, color or heatmap", name));
+        print_maze(&input, style);
+        return;
+    }
+
     let part1_start = std::time::Instant::now();
     let part1 = solve_part1(&input);
     println!("Part 1: {} ({:?})", part1, part1_start.elapsed());
@@ -228,28 +439,191 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     const SAMPLE_INPUT: &str = include_str!("sample_input.txt");
     const SAMPLE_INPUT_2: &str = include_str!("sample_input_2.txt");
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.maze.len(), 5);
         assert_eq!(input.maze[0].len(), 5);
         assert_eq!(input.start, (0, 2));
+        assert_eq!(input.maze[2][0], Pipe::SouthEast);
+        assert_eq!(input.maze[2][1], Pipe::NorthWest);
+        assert_eq!(input.maze[0][0], Pipe::Ground);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse_maze("F7\nLJ"), Err(MazeError::NoStart));
+        assert_eq!(parse_maze("S7\nLS"), Err(MazeError::MultipleStarts));
+        assert_eq!(
+            parse_maze("S7\nLx"),
+            Err(MazeError::UnknownTile {
+                tile: 'x',
+                x: 1,
+                y: 1
+            })
+        );
+        assert_eq!(parse_maze("S7\nL"), Err(MazeError::RaggedRow { y: 1 }));
+        // Nothing leads into S, only one pipe does, or three do.
+        assert_eq!(
+            parse_maze("...\n.S.\n..."),
+            Err(MazeError::StartConnections(0))
+        );
+        assert_eq!(
+            parse_maze(".|.\n.S.\n..."),
+            Err(MazeError::StartConnections(1))
+        );
+        let error = parse_maze(".|.\n-S-\n...").unwrap_err();
+        assert_eq!(error, MazeError::StartConnections(3));
+        assert_eq!(
+            error.to_string(),
+            "Start tile S must connect to exactly two pipes, but 3 lead into it"
+        );
     }
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 8)
     }
 
+    // A small loop with S placed on each of its six kinds of pipe in turn.
+    const SQUARE_LOOP: &str = "\
+.....
+.F-7.
+.|.|.
+.L-J.
+.....";
+
+    fn maze_with_start(maze: &str, (x, y): (usize, usize)) -> PipeMaze {
+        let mut lines: Vec<String> = maze.lines().map(str::to_string).collect();
+        lines[y].replace_range(x..x + 1, "S");
+        parse_input(&lines.join("\n"))
+    }
+
+    #[test]
+    fn test_infer_start() {
+        let wide_loop = "\
+......
+.F--7.
+.|..|.
+.L--J.
+......";
+        // The maze, where S goes, the pipe it stands for, and the answers to both parts.
+        let cases = [
+            (SQUARE_LOOP, (1, 1), 'F', 4, 1),
+            (SQUARE_LOOP, (3, 1), '7', 4, 1),
+            (SQUARE_LOOP, (1, 3), 'L', 4, 1),
+            (SQUARE_LOOP, (3, 3), 'J', 4, 1),
+            (SQUARE_LOOP, (1, 2), '|', 4, 1),
+            (wide_loop, (2, 1), '-', 5, 2),
+        ];
+        for &(maze, start, shape, farthest, inside) in cases.iter() {
+            let input = maze_with_start(maze, start);
+            assert_eq!(input.start, start);
+            assert_eq!(Some(input.pipe(start)), Pipe::from_char(shape));
+            assert_eq!(solve_part1(&input), farthest);
+            assert_eq!(solve_part2(&input), inside);
+        }
+    }
+
+    #[test]
+    fn test_trace_loop() {
+        let input = parse_input(SAMPLE_INPUT);
+        let path = trace_loop(&input);
+        assert_eq!(path.len(), 16);
+        assert_eq!(path[0], (0, 2));
+        assert_eq!(path.iter().collect::<HashSet<_>>().len(), 16);
+        // Each tile is next to the one before it, all the way round.
+        for i in 0..path.len() {
+            let ((x1, y1), (x2, y2)) = (path[i], path[(i + 1) % path.len()]);
+            assert_eq!(
+                (x1 as i32 - x2 as i32).abs() + (y1 as i32 - y2 as i32).abs(),
+                1
+            );
+        }
+        let vertices = loop_vertices(&input, &path);
+        assert_eq!(vertices.len(), 12);
+        assert_eq!(double_area(&vertices), 2 * 8);
+    }
+
+    #[test]
+    fn test_count_inside() {
+        // Squeezing between pipes does not count as being outside.
+        let maze = "\
+..........
+.S------7.
+.|F----7|.
+.||....||.
+.||....||.
+.|L-7F-J|.
+.|..||..|.
+.L--JL--J.
+..........";
+        let input = parse_input(maze);
+        assert_eq!(solve_part2(&input), 4);
+    }
+
+    #[test]
+    fn test_render_maze() {
+        let input = parse_input(SAMPLE_INPUT);
+        assert_eq!(
+            render_maze(&input, &find_distances(&input), Style::Plain),
+            "OO┌┐O\nO┌┘│O\n┌┘I└┐\n│┌──┘\n└┘OOO\n"
+        );
+        let input = maze_with_start(SQUARE_LOOP, (1, 1));
+        let distances = find_distances(&input);
+        let plain = render_maze(&input, &distances, Style::Plain);
+        assert_eq!(plain.lines().nth(2), Some("O│I│O"));
+
+        // Every tile is drawn in colour, with the interior shaded differently.
+        let color = render_maze(&input, &distances, Style::Color);
+        assert_eq!(color.matches(RESET).count(), 25);
+        assert_eq!(color.matches(INSIDE_COLOR).count(), 1);
+        assert_eq!(color.matches(OUTSIDE_COLOR).count(), 16);
+        assert_eq!(color.matches(LOOP_COLOR).count(), 8);
+        assert!(color.contains(&format!("{}·{}", INSIDE_COLOR, RESET)));
+
+        // S is the coolest colour and the far side of the loop the hottest.
+        let heatmap = render_maze(&input, &distances, Style::Heatmap);
+        assert!(heatmap.contains(&format!("{}┌{}", heatmap_color(0, 4), RESET)));
+        assert!(heatmap.contains(&format!("{}┘{}", heatmap_color(4, 4), RESET)));
+        assert_eq!(heatmap_color(0, 4), "\x1b[1;38;5;21m");
+        assert_eq!(heatmap_color(4, 4), "\x1b[1;38;5;196m");
+
+        assert_eq!(Style::from_name("heatmap"), Some(Style::Heatmap));
+        assert_eq!(Style::from_name("colour"), None);
+    }
+
+    #[test]
+    fn test_classify_tiles() {
+        // The interior found by scanning agrees with the count from Pick's theorem.
+        for maze in [SAMPLE_INPUT, SAMPLE_INPUT_2].iter() {
+            let input = parse_input(maze);
+            let tiles = classify_tiles(&input, &find_distances(&input));
+            let inside = tiles
+                .iter()
+                .flatten()
+                .filter(|&&tile| tile == Tile::Inside)
+                .count();
+            assert_eq!(inside as SolutionType, solve_part2(&input));
+            let on_loop = tiles
+                .iter()
+                .flatten()
+                .filter(|&&tile| tile == Tile::Loop)
+                .count();
+            assert_eq!(on_loop as SolutionType, 2 * solve_part1(&input));
+        }
+    }
+
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT_2.to_string());
+        let input = parse_input(SAMPLE_INPUT_2);
         let result = solve_part2(&input);
         assert_eq!(result, 10)
     }
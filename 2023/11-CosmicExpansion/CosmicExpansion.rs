@@ -3,18 +3,25 @@
 
 use std::fs::read_to_string;
 
+#[path = "../common/bigint.rs"]
+mod bigint;
+use bigint::BigInt;
+
 type InputType = Vec<Vec<char>>;
-type SolutionType = i64;
-type GalaxyLocation = (SolutionType, SolutionType);
+// Coordinates fit in an i128 for any sensible expansion, but summing them over
+// every pair of galaxies can overflow, so the totals are unbounded.
+type SolutionType = BigInt;
+type Coordinate = i128;
+type GalaxyLocation = (Coordinate, Coordinate);
 
-fn parse_input(input_str: String) -> InputType {
+fn parse_input(input_str: &str) -> InputType {
     input_str
         .lines()
         .map(|line| line.chars().collect())
         .collect()
 }
 
-fn get_expansion_rows_and_columns(input: &InputType) -> (Vec<usize>, Vec<usize>) {
+fn get_galaxy_counts(input: &InputType) -> (Vec<usize>, Vec<usize>) {
     let mut row_counts = vec![0; input.len()];
     let mut col_counts = vec![0; input[0].len()];
     for row in 0..input.len() {
@@ -25,67 +32,62 @@ fn get_expansion_rows_and_columns(input: &InputType) -> (Vec<usize>, Vec<usize>)
             }
         }
     }
-    let mut expansion_rows = Vec::new();
-    let mut expansion_cols = Vec::new();
-    for (row, &count) in row_counts.iter().enumerate() {
-        if count == 0 {
-            expansion_rows.push(row);
-        }
-    }
-    for (col, &count) in col_counts.iter().enumerate() {
-        if count == 0 {
-            expansion_cols.push(col);
-        }
+    (row_counts, col_counts)
+}
+
+// Returns the expanded coordinate of each row (or column) given the number of galaxies in each.
+// Every empty row before a row pushes it out by `expand_by`.
+fn get_expanded_coordinates(galaxy_counts: &[usize], expand_by: Coordinate) -> Vec<Coordinate> {
+    let mut coordinates = Vec::with_capacity(galaxy_counts.len());
+    let mut next = 0;
+    for &count in galaxy_counts {
+        coordinates.push(next);
+        next += if count == 0 { expand_by + 1 } else { 1 };
     }
-    (expansion_rows, expansion_cols)
+    coordinates
 }
 
-fn get_galaxies(input: &InputType, expand_by: SolutionType) -> Vec<GalaxyLocation> {
+fn get_galaxies(input: &InputType, expand_by: Coordinate) -> Vec<GalaxyLocation> {
+    let (row_counts, col_counts) = get_galaxy_counts(input);
+    let rows = get_expanded_coordinates(&row_counts, expand_by);
+    let cols = get_expanded_coordinates(&col_counts, expand_by);
     let mut result = Vec::new();
-    let (expansion_rows, expansion_cols) = get_expansion_rows_and_columns(input);
-    let mut row_offset = 0;
     for (row, line) in input.iter().enumerate() {
-        let mut col_offset = 0;
-        if expansion_rows.contains(&row) {
-            row_offset += expand_by;
-            continue;
-        }
         for (col, &cell) in line.iter().enumerate() {
-            if expansion_cols.contains(&col) {
-                col_offset += expand_by;
-                continue;
-            }
             if cell == '#' {
-                result.push((
-                    row as SolutionType + row_offset,
-                    col as SolutionType + col_offset,
-                ));
+                result.push((rows[row], cols[col]));
             }
         }
     }
     result
 }
 
-fn get_pairs(galaxies: &[GalaxyLocation]) -> Vec<(GalaxyLocation, GalaxyLocation)> {
-    let mut result = Vec::new();
-    for (i, &galaxy1) in galaxies.iter().enumerate() {
-        for &galaxy2 in &galaxies[i + 1..] {
-            result.push((galaxy1, galaxy2));
-        }
-    }
-    result
+// Distance between the galaxies at indices `i` and `j` of `get_galaxies`, in reading order.
+// The total is computed per axis without visiting pairs.
+fn pair_distance(galaxies: &[GalaxyLocation], i: usize, j: usize) -> Coordinate {
+    let (galaxy1, galaxy2) = (galaxies[i], galaxies[j]);
+    (galaxy1.0 - galaxy2.0).abs() + (galaxy1.1 - galaxy2.1).abs()
 }
 
-fn distance(galaxy1: GalaxyLocation, galaxy2: GalaxyLocation) -> SolutionType {
-    (galaxy1.0 - galaxy2.0).abs() + (galaxy1.1 - galaxy2.1).abs()
+// Returns the sum of |a - b| over all pairs of values.
+// Once sorted, each value is larger than every value before it, so it contributes
+// value * (number of earlier values) - (sum of earlier values).
+fn sum_of_differences(mut values: Vec<Coordinate>) -> SolutionType {
+    values.sort_unstable();
+    let mut total = BigInt::zero();
+    let mut prefix_sum = BigInt::zero();
+    for (i, &value) in values.iter().enumerate() {
+        let value = BigInt::from(value);
+        total += &(&value * &BigInt::from(i)) - &prefix_sum;
+        prefix_sum += value;
+    }
+    total
 }
 
-fn solve(input: &InputType, expand_by: SolutionType) -> SolutionType {
+fn solve(input: &InputType, expand_by: Coordinate) -> SolutionType {
     let galaxies = get_galaxies(input, expand_by);
-    get_pairs(&galaxies)
-        .iter()
-        .map(|pair| distance(pair.0, pair.1))
-        .sum()
+    sum_of_differences(galaxies.iter().map(|g| g.0).collect())
+        + sum_of_differences(galaxies.iter().map(|g| g.1).collect())
 }
 
 fn solve_part1(input: &InputType) -> SolutionType {
@@ -98,9 +100,30 @@ fn solve_part2(input: &InputType) -> SolutionType {
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
+    // Galaxies are numbered from 1, as in the puzzle description.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--pair") {
+        let number = |arg: Option<&String>| -> usize {
+            arg.and_then(|arg| arg.parse().ok())
+                .filter(|&number| number > 0)
+                .expect("Usage: --pair <galaxy> <galaxy> [expand_by]")
+        };
+        let (galaxy1, galaxy2) = (number(args.get(1)), number(args.get(2)));
+        let expand_by = args.get(3).map_or(1, |arg| arg.parse().unwrap());
+        let galaxies = get_galaxies(&input, expand_by);
+        println!(
+            "Distance from galaxy {} to galaxy {}: {}",
+            galaxy1,
+            galaxy2,
+            pair_distance(&galaxies, galaxy1 - 1, galaxy2 - 1)
+        );
+        return;
+    }
+
     let part1_start = std::time::Instant::now();
     let part1 = solve_part1(&input);
     println!("Part 1: {} ({:?})", part1, part1_start.elapsed());
@@ -118,37 +141,84 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.len(), 10);
         assert_eq!(input[0].len(), 10);
     }
 
     #[test]
     fn test_get_galaxies() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = get_galaxies(&input, 1);
         assert_eq!(result.len(), 9);
     }
 
     #[test]
-    fn test_get_pairs() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+    fn test_get_expanded_coordinates() {
+        assert_eq!(
+            get_expanded_coordinates(&[1, 0, 2, 0, 0, 1], 1),
+            vec![0, 1, 3, 4, 6, 8]
+        );
+        assert_eq!(get_expanded_coordinates(&[1, 0, 2], 9), vec![0, 1, 11]);
+    }
+
+    #[test]
+    fn test_pair_distance() {
+        // Galaxies are numbered from 1 in the puzzle description.
+        let input = parse_input(SAMPLE_INPUT);
+        let galaxies = get_galaxies(&input, 1);
+        assert_eq!(pair_distance(&galaxies, 4, 8), 9);
+        assert_eq!(pair_distance(&galaxies, 0, 6), 15);
+        assert_eq!(pair_distance(&galaxies, 2, 5), 17);
+        assert_eq!(pair_distance(&galaxies, 7, 8), 5);
+        assert_eq!(pair_distance(&galaxies, 8, 7), 5);
+        assert_eq!(pair_distance(&galaxies, 3, 3), 0);
+        // Galaxies 5 and 9 are separated by one empty row and one empty column,
+        // each of which is now 98 wider than in part 1.
+        let galaxies = get_galaxies(&input, 99);
+        assert_eq!(pair_distance(&galaxies, 4, 8), 9 + 2 * 98);
+    }
+
+    #[test]
+    fn test_sum_of_differences() {
+        let input = parse_input(SAMPLE_INPUT);
         let galaxies = get_galaxies(&input, 1);
-        let result = get_pairs(&galaxies);
-        assert_eq!(result.len(), 36);
+        let mut pairs = 0;
+        let mut brute_force = 0_i128;
+        for i in 0..galaxies.len() {
+            for j in i + 1..galaxies.len() {
+                pairs += 1;
+                brute_force += pair_distance(&galaxies, i, j);
+            }
+        }
+        assert_eq!(pairs, 36);
+        assert_eq!(solve(&input, 1), BigInt::from(brute_force));
+        assert_eq!(sum_of_differences(vec![5, 1, 3]), BigInt::from(8));
+    }
+
+    #[test]
+    fn test_huge_expansion() {
+        let input = parse_input(SAMPLE_INPUT);
+        let expand_by = 10_i128.pow(37);
+        // The sample has 82 empty rows and columns crossed between pairs in total,
+        // as shown by the part 2 examples growing by 82 for each extra unit of expansion.
+        // The total is larger than i128::MAX even though every coordinate fits.
+        let expected = &BigInt::from(292) + &(&BigInt::from(82) * &BigInt::from(expand_by));
+        assert!(expected > BigInt::from(i128::MAX));
+        assert_eq!(solve(&input, expand_by), expected);
     }
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
-        assert_eq!(result, 374)
+        assert_eq!(result, BigInt::from(374))
     }
 
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve(&input, 99);
-        assert_eq!(result, 8410)
+        assert_eq!(result, BigInt::from(8410))
     }
 }
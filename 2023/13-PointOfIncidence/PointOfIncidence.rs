@@ -4,237 +4,161 @@
 use std::fs::read_to_string;
 use std::panic;
 
+#[path = "../common/grid.rs"]
+mod grid;
+use grid::GridView;
+
+#[path = "../common/sections.rs"]
+mod sections;
+use sections::{section_lines, sections};
+
 type PatternType = Vec<Vec<char>>;
 type InputType = Vec<PatternType>;
 type SolutionType = u32;
 
-fn parse_pattern(pattern_str: String) -> PatternType {
-    pattern_str
-        .lines()
+fn parse_pattern(section: &str) -> PatternType {
+    section_lines(section)
         .map(|line| line.chars().collect())
         .collect()
 }
 
-fn parse_input(input_str: String) -> InputType {
-    input_str
-        .split("\n\n")
-        .map(|pattern_str| parse_pattern(pattern_str.to_string()))
-        .collect()
-}
-
-fn find_equal_columns(pattern: &PatternType) -> Vec<usize> {
-    let mut equal_columns = Vec::new();
-    for col_num in 0..pattern[0].len() - 1 {
-        if pattern.iter().all(|row| row[col_num] == row[col_num + 1]) {
-            equal_columns.push(col_num);
-        }
-    }
-    equal_columns
-}
-
-fn find_equal_rows(pattern: &PatternType) -> Vec<usize> {
-    let mut equal_rows = Vec::new();
-    for row_num in 0..pattern.len() - 1 {
-        if pattern[row_num] == pattern[row_num + 1] {
-            equal_rows.push(row_num);
-        }
-    }
-    equal_rows
-}
-
-fn is_reflection_vertical(pattern: &PatternType, col: usize) -> bool {
-    if col == 0 || col >= pattern[0].len() - 2 {
-        return true;
-    }
-    let mut left_col = col - 1;
-    let mut right_col = col + 2;
-    loop {
-        if pattern.iter().any(|row| row[left_col] != row[right_col]) {
-            return false;
-        }
-        if left_col == 0 || right_col >= pattern[0].len() - 2 {
-            return true;
-        }
-        left_col -= 1;
-        right_col += 1;
-    }
-}
-
-fn is_reflection_horizontal(pattern: &PatternType, row: usize) -> bool {
-    if row == 0 || row >= pattern.len() - 2 {
-        return true;
-    }
-    let mut top_row = row - 1;
-    let mut bottom_row = row + 2;
-    loop {
-        if pattern[top_row] != pattern[bottom_row] {
-            return false;
-        }
-        if top_row == 0 || bottom_row >= pattern.len() - 2 {
-            return true;
-        }
-        top_row -= 1;
-        bottom_row += 1;
-    }
-}
-
-fn find_vertical_reflection(pattern: &PatternType) -> Option<usize> {
-    let equal_columns = find_equal_columns(pattern);
-    for col in equal_columns {
-        if is_reflection_vertical(pattern, col) {
-            return Some(col);
-        }
-    }
-    None
-}
-
-fn find_horizontal_reflection(pattern: &PatternType) -> Option<usize> {
-    let equal_rows = find_equal_rows(pattern);
-    for row in equal_rows {
-        if is_reflection_horizontal(pattern, row) {
-            return Some(row);
-        }
-    }
-    None
-}
-
-fn summarize_pattern(pattern: &PatternType) -> SolutionType {
-    if let Some(col) = find_vertical_reflection(pattern) {
-        return (col + 1) as SolutionType;
-    }
-    if let Some(row) = find_horizontal_reflection(pattern) {
-        return (row + 1) as SolutionType * 100;
-    }
-    panic!("No reflection found");
+fn parse_input(input_str: &str) -> InputType {
+    sections(input_str).map(parse_pattern).collect()
 }
 
-fn solve_part1(input: &InputType) -> SolutionType {
-    input.iter().map(|pattern| summarize_pattern(pattern)).sum()
+// Each row of the view is encoded as a bitmask with bit i set when cell i is '#',
+// so the number of differing cells between two lines is the popcount of their xor.
+// Columns are encoded as the rows of the transposed view.
+fn encode_lines(view: &GridView<char>) -> Vec<u64> {
+    assert!(view.width() <= 64, "Pattern too large");
+    (0..view.height())
+        .map(|row| {
+            view.row(row)
+                .enumerate()
+                .filter(|(_, &c)| c == '#')
+                .fold(0, |mask, (i, _)| mask | 1 << i)
+        })
+        .collect()
 }
 
-fn find_num_column_differences(pattern: &PatternType, col1: usize, col2: usize, max: usize) -> usize {
-    let mut num_differences = 0;
-    for row in pattern {
-        if row[col1] != row[col2] {
-            num_differences += 1;
-            if num_differences > max {
-                return num_differences;
+// Returns every mirror position (the number of lines before the mirror) where the
+// mirrored lines differ in exactly `differences` cells in total.
+fn find_mirror_lines(lines: &[u64], differences: u32) -> Vec<usize> {
+    let mut result = Vec::new();
+    for position in 1..lines.len() {
+        let mut total = 0;
+        for k in 0..position.min(lines.len() - position) {
+            total += (lines[position - 1 - k] ^ lines[position + k]).count_ones();
+            if total > differences {
+                break;
             }
         }
-    }
-    return num_differences
-}
-
-fn find_equal_columns_2(pattern: &PatternType) -> Vec<usize> {
-    let mut almost_equal_columns = Vec::new();
-    for col_num in 0..pattern[0].len() - 1 {
-        if find_num_column_differences(&pattern, col_num, col_num + 1, 1) <= 1 {
-            almost_equal_columns.push(col_num);
+        if total == differences {
+            result.push(position);
         }
     }
-    almost_equal_columns
+    result
 }
 
-fn find_num_row_differences(pattern: &PatternType, row1: usize, row2: usize, max: usize) -> usize {
-    let mut num_differences = 0;
-    for col_num in 0..pattern[0].len() {
-        if pattern[row1][col_num] != pattern[row2][col_num] {
-            num_differences += 1;
-            if num_differences > max {
-                return num_differences;
-            }
+// Returns the pairs of mirrored cells that differ for a mirror at `position`,
+// each as (line, index) for the line before the mirror and its reflection.
+fn find_mirror_differences(
+    lines: &[u64],
+    position: usize,
+) -> Vec<((usize, usize), (usize, usize))> {
+    let mut result = Vec::new();
+    for k in 0..position.min(lines.len() - position) {
+        let (before, after) = (position - 1 - k, position + k);
+        let mut diff = lines[before] ^ lines[after];
+        while diff != 0 {
+            let index = diff.trailing_zeros() as usize;
+            result.push(((before, index), (after, index)));
+            diff &= diff - 1;
         }
     }
-    return num_differences
+    result
 }
 
-fn find_equal_rows_2(pattern: &PatternType) -> Vec<usize> {
-    let mut almost_equal_rows = Vec::new();
-    for row_num in 0..pattern.len() - 1 {
-        for col_num in 0..pattern[0].len() {
-            if find_num_row_differences(&pattern, row_num, row_num + 1, 1) <= 1 {
-                almost_equal_rows.push(row_num);
-            }
-        }
-    }
-    almost_equal_rows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Axis {
+    // A vertical mirror between two columns.
+    Vertical,
+    // A horizontal mirror between two rows.
+    Horizontal,
 }
 
-fn is_reflection_vertical_2(pattern: &PatternType, col: usize) -> bool {
-    if col == 0 || col >= pattern[0].len() - 2 {
-        return true;
-    }
-    let mut left_col = col - 1;
-    let mut right_col = col + 2;
-    loop {
-        if find_num_column_differences(&pattern, left_col, right_col, 1) != 1 {
-            return false;
-        }
-        if left_col == 0 || right_col >= pattern[0].len() - 2 {
-            return true;
-        }
-        left_col -= 1;
-        right_col += 1;
-    }
+type Cell = (usize, usize);
+
+#[derive(Debug, PartialEq, Eq)]
+struct Reflection {
+    axis: Axis,
+    // The number of columns left of a vertical mirror or rows above a horizontal one.
+    position: usize,
+    // The (row, col) cells that differ from their reflection, paired with that reflection.
+    // Flipping either cell of every pair makes the reflection perfect.
+    smudges: Vec<(Cell, Cell)>,
 }
 
-fn is_reflection_horizontal_2(pattern: &PatternType, row: usize) -> bool {
-    if row == 0 || row >= pattern.len() - 2 {
-        return true;
-    }
-    let mut top_row = row - 1;
-    let mut bottom_row = row + 2;
-    loop {
-        for col_num in 0..pattern[0].len() {
-            if find_num_row_differences(&pattern, top_row, bottom_row, 1) != 1 {
-                return false;
-            }
+impl Reflection {
+    fn summarize(&self) -> SolutionType {
+        match self.axis {
+            Axis::Vertical => self.position as SolutionType,
+            Axis::Horizontal => self.position as SolutionType * 100,
         }
-        if top_row == 0 || bottom_row >= pattern.len() - 2 {
-            return true;
-        }
-        top_row -= 1;
-        bottom_row += 1;
     }
 }
 
-fn find_vertical_reflection_2(pattern: &PatternType) -> Option<usize> {
-    let equal_columns = find_equal_columns_2(pattern);
-    for col in equal_columns {
-        if is_reflection_vertical_2(pattern, col) {
-            return Some(col);
-        }
-    }
-    None
+// Finds every reflection whose two sides differ in exactly `differences` cells,
+// vertical mirrors first.
+fn find_reflections(pattern: &PatternType, differences: u32) -> Vec<Reflection> {
+    let view = GridView::new(pattern);
+    let columns = encode_lines(&view.transpose());
+    let rows = encode_lines(&view);
+    let vertical = find_mirror_lines(&columns, differences)
+        .into_iter()
+        .map(|position| Reflection {
+            axis: Axis::Vertical,
+            position,
+            smudges: find_mirror_differences(&columns, position)
+                .into_iter()
+                .map(|((col1, row1), (col2, row2))| ((row1, col1), (row2, col2)))
+                .collect(),
+        });
+    let horizontal = find_mirror_lines(&rows, differences)
+        .into_iter()
+        .map(|position| Reflection {
+            axis: Axis::Horizontal,
+            position,
+            smudges: find_mirror_differences(&rows, position),
+        });
+    vertical.chain(horizontal).collect()
 }
 
-fn find_horizontal_reflection_2(pattern: &PatternType) -> Option<usize> {
-    let equal_rows = find_equal_rows_2(pattern);
-    for row in equal_rows {
-        if is_reflection_horizontal_2(pattern, row) {
-            return Some(row);
-        }
+fn summarize_pattern(pattern: &PatternType, differences: u32) -> SolutionType {
+    match find_reflections(pattern, differences).first() {
+        Some(reflection) => reflection.summarize(),
+        None => panic!("No reflection found"),
     }
-    None
 }
 
-fn summarize_pattern_2(pattern: &PatternType) -> SolutionType {
-    if let Some(col) = find_vertical_reflection_2(pattern) {
-        return (col + 1) as SolutionType;
-    }
-    if let Some(row) = find_horizontal_reflection_2(pattern) {
-        return (row + 1) as SolutionType * 100;
-    }
-    panic!("No reflection found");
+fn solve_part1(input: &InputType) -> SolutionType {
+    input
+        .iter()
+        .map(|pattern| summarize_pattern(pattern, 0))
+        .sum()
 }
 
 fn solve_part2(input: &InputType) -> SolutionType {
-    input.iter().map(|pattern| summarize_pattern_2(pattern)).sum()
+    input
+        .iter()
+        .map(|pattern| summarize_pattern(pattern, 1))
+        .sum()
 }
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
     let part1_start = std::time::Instant::now();
@@ -254,72 +178,136 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.len(), 2);
         assert_eq!(input[0].len(), 7);
         assert_eq!(input[0][0].len(), 9);
     }
 
     #[test]
-    fn test_find_equal_columns() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        assert_eq!(find_equal_columns(&input[0]), vec![4]);
-        assert_eq!(find_equal_columns(&input[1]), vec![2, 6]);
+    fn test_encode() {
+        let input = parse_input(SAMPLE_INPUT);
+        let view = GridView::new(&input[0]);
+        let rows = encode_lines(&view);
+        let columns = encode_lines(&view.transpose());
+        assert_eq!(rows.len(), 7);
+        assert_eq!(columns.len(), 9);
+        // #.##..##.
+        assert_eq!(rows[0], 0b011001101);
+        // First column, top to bottom: #.##..#
+        assert_eq!(columns[0], 0b1001101);
+
+        let square = vec![vec!['#'; 64]; 64];
+        assert_eq!(encode_lines(&GridView::new(&square))[63], u64::MAX);
     }
 
     #[test]
-    fn test_find_equal_rows() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        assert_eq!(find_equal_rows(&input[0]), vec![2]);
-        assert_eq!(find_equal_rows(&input[1]), vec![3]);
+    #[should_panic(expected = "Pattern too large")]
+    fn test_encode_too_wide() {
+        let pattern = vec![vec!['.'; 65]; 3];
+        encode_lines(&GridView::new(&pattern));
     }
 
     #[test]
-    fn test_is_reflection_vertical() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        assert!(is_reflection_vertical(&input[0], 4));
-        assert!(!is_reflection_vertical(&input[1], 2));
-        assert!(!is_reflection_vertical(&input[1], 6));
+    fn test_find_mirror_lines() {
+        let input = parse_input(SAMPLE_INPUT);
+        let views = [GridView::new(&input[0]), GridView::new(&input[1])];
+        let rows: Vec<Vec<u64>> = views.iter().map(|view| encode_lines(view)).collect();
+        let columns: Vec<Vec<u64>> = views
+            .iter()
+            .map(|view| encode_lines(&view.transpose()))
+            .collect();
+        assert_eq!(find_mirror_lines(&columns[0], 0), vec![5]);
+        assert_eq!(find_mirror_lines(&rows[0], 0), vec![]);
+        assert_eq!(find_mirror_lines(&columns[1], 0), vec![]);
+        assert_eq!(find_mirror_lines(&rows[1], 0), vec![4]);
+        assert_eq!(find_mirror_lines(&rows[0], 1), vec![3]);
+        assert_eq!(find_mirror_lines(&rows[1], 1), vec![1]);
     }
 
     #[test]
-    fn test_is_reflection_horizontal() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        assert!(!is_reflection_horizontal(&input[0], 2));
-        assert!(is_reflection_horizontal(&input[1], 3));
+    fn test_find_reflections() {
+        let input = parse_input(SAMPLE_INPUT);
+        assert_eq!(
+            find_reflections(&input[0], 0),
+            vec![Reflection {
+                axis: Axis::Vertical,
+                position: 5,
+                smudges: vec![]
+            }]
+        );
+        assert_eq!(
+            find_reflections(&input[1], 0),
+            vec![Reflection {
+                axis: Axis::Horizontal,
+                position: 4,
+                smudges: vec![]
+            }]
+        );
     }
 
     #[test]
-    fn test_find_vertical_reflection() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        assert_eq!(find_vertical_reflection(&input[0]), Some(4));
-        assert_eq!(find_vertical_reflection(&input[1]), None);
+    fn test_find_smudges() {
+        // The puzzle fixes the first pattern at its top-left cell
+        // and the second pattern at the fifth cell of its second row.
+        let input = parse_input(SAMPLE_INPUT);
+        assert_eq!(
+            find_reflections(&input[0], 1),
+            vec![Reflection {
+                axis: Axis::Horizontal,
+                position: 3,
+                smudges: vec![((0, 0), (5, 0))]
+            }]
+        );
+        assert_eq!(
+            find_reflections(&input[1], 1),
+            vec![Reflection {
+                axis: Axis::Horizontal,
+                position: 1,
+                smudges: vec![((0, 4), (1, 4))]
+            }]
+        );
     }
 
     #[test]
-    fn test_find_horizontal_reflection() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        assert_eq!(find_horizontal_reflection(&input[0]), None);
-        assert_eq!(find_horizontal_reflection(&input[1]), Some(3));
+    fn test_fixing_smudges_makes_reflection_perfect() {
+        let input = parse_input(SAMPLE_INPUT);
+        for differences in 1..4 {
+            for pattern in &input {
+                for reflection in find_reflections(pattern, differences) {
+                    assert_eq!(reflection.smudges.len(), differences as usize);
+                    let mut fixed = pattern.clone();
+                    for &((row, col), _) in &reflection.smudges {
+                        fixed[row][col] = if fixed[row][col] == '#' { '.' } else { '#' };
+                    }
+                    let perfect = find_reflections(&fixed, 0);
+                    assert!(perfect
+                        .iter()
+                        .any(|r| r.axis == reflection.axis && r.position == reflection.position));
+                }
+            }
+        }
     }
 
     #[test]
     fn test_summarize_pattern() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        assert_eq!(summarize_pattern(&input[0]), 5);
-        assert_eq!(summarize_pattern(&input[1]), 400);
+        let input = parse_input(SAMPLE_INPUT);
+        assert_eq!(summarize_pattern(&input[0], 0), 5);
+        assert_eq!(summarize_pattern(&input[1], 0), 400);
+        assert_eq!(summarize_pattern(&input[0], 1), 300);
+        assert_eq!(summarize_pattern(&input[1], 1), 100);
     }
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 405)
     }
 
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part2(&input);
         assert_eq!(result, 400)
     }
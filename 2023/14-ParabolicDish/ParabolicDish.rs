@@ -1,13 +1,18 @@
 // ParabolicDish
 // https://adventofcode.com/2023/day/14
 
+use std::collections::HashMap;
 use std::fs::read_to_string;
-use std::panic;
+use std::ops::Range;
+
+#[path = "../common/grid.rs"]
+mod grid;
+use grid::GridView;
 
 type InputType = Vec<Vec<char>>;
 type SolutionType = u32;
 
-fn parse_input(input_str: String) -> InputType {
+fn parse_input(input_str: &str) -> InputType {
     input_str
         .lines()
         .map(|line| line.chars().collect())
@@ -25,158 +30,360 @@ fn print_map(input: &InputType) {
     println!();
 }
 
-fn move_rocks_north(input: &InputType) -> InputType {
-    let mut result = input.clone();
-    for col in 0..input[0].len() {
-        let mut free_row: Option<usize> = None;
-        for row in 0..input.len() {
-            match input[row][col] {
-                '.' => {
-                    if free_row.is_none() {
-                        free_row = Some(row);
-                    }
-                }
-                '#' => {
-                    free_row = None;
-                }
-                'O' => {
-                    if let Some(new_row) = free_row {
-                        result[new_row][col] = 'O';
-                        result[row][col] = '.';
-                        free_row = Some(new_row + 1);
-                    }
-                }
-                _ => panic!("Invalid input"),
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+// A run of cells from bit `start` up to bit `end` of row or column `line`, bounded by
+// cube rocks or the edges of the platform. Round rocks never leave the segment they
+// start in.
+#[derive(Clone, Debug, PartialEq)]
+struct Segment {
+    line: usize,
+    start: u32,
+    end: u32,
+}
+
+impl Segment {
+    fn mask(&self) -> u128 {
+        low_bits(self.end) & !low_bits(self.start)
+    }
+
+    // The cells that `count` rocks fill when packed against the start of the segment
+    // (or against its end).
+    fn packed(&self, count: u32, towards_end: bool) -> Range<u32> {
+        if towards_end {
+            self.end - count..self.end
+        } else {
+            self.start..self.start + count
+        }
+    }
+}
+
+// Splits each line of `len` cells at the cube rocks, leaving out empty segments.
+fn find_segments(cube: &[u128], len: usize) -> Vec<Segment> {
+    let len = len as u32;
+    let mut segments = Vec::new();
+    for (line, &cube) in cube.iter().enumerate() {
+        let mut start = 0;
+        let mut cubes = cube;
+        while start < len {
+            let end = cubes.trailing_zeros().min(len);
+            if end > start {
+                segments.push(Segment { line, start, end });
             }
+            start = end + 1;
+            cubes &= cubes.wrapping_sub(1);
         }
     }
-    result
+    segments
 }
 
-fn move_rocks_south(input: &InputType) -> InputType {
-    let mut result = input.clone();
-    for col in 0..input[0].len() {
-        let mut free_row: Option<usize> = None;
-        for row in (0..input.len()).rev() {
-            match input[row][col] {
-                '.' => {
-                    if free_row.is_none() {
-                        free_row = Some(row);
-                    }
-                }
-                '#' => {
-                    free_row = None;
-                }
-                'O' => {
-                    if let Some(new_row) = free_row {
-                        result[new_row][col] = 'O';
-                        result[row][col] = '.';
-                        free_row = Some(new_row - 1);
-                    }
-                }
-                _ => panic!("Invalid input"),
+// The lowest `count` bits set, for counts up to 128.
+fn low_bits(count: u32) -> u128 {
+    if count >= 128 {
+        u128::MAX
+    } else {
+        (1 << count) - 1
+    }
+}
+
+// The segments of every row (or every column), with a flat table giving, for each cell
+// of each segment in turn, the index of the column (or row) segment that also holds it.
+#[derive(Clone, Debug, PartialEq)]
+struct SegmentTable {
+    segments: Vec<Segment>,
+    // The cells of segment i are crossing[offsets[i]..offsets[i + 1]].
+    offsets: Vec<usize>,
+    crossing: Vec<u16>,
+    crossing_segments: usize,
+}
+
+impl SegmentTable {
+    // `cube` has a bitset per line and `cube_crossing` a bitset per line the other way.
+    fn new(cube: &[u128], cube_crossing: &[u128]) -> Self {
+        let segments = find_segments(cube, cube_crossing.len());
+        let others = find_segments(cube_crossing, cube.len());
+        // The index of the crossing segment holding each cell, by crossing line and bit.
+        let mut index = vec![vec![0; cube.len()]; cube_crossing.len()];
+        for (i, other) in others.iter().enumerate() {
+            for bit in other.start..other.end {
+                index[other.line][bit as usize] = i as u16;
+            }
+        }
+        let mut offsets = vec![0];
+        let mut crossing = Vec::new();
+        for segment in &segments {
+            crossing
+                .extend((segment.start..segment.end).map(|bit| index[bit as usize][segment.line]));
+            offsets.push(crossing.len());
+        }
+        Self {
+            segments,
+            offsets,
+            crossing,
+            crossing_segments: others.len(),
+        }
+    }
+
+    // Given the number of rocks in each segment, packed against the start (or the end) of
+    // their segments, counts the rocks in each crossing segment into `result`.
+    fn count_crossing(&self, counts: &[u16], towards_end: bool, result: &mut Vec<u16>) {
+        result.clear();
+        result.resize(self.crossing_segments, 0);
+        for (window, &count) in self.offsets.windows(2).zip(counts) {
+            let cells = &self.crossing[window[0]..window[1]];
+            let count = count as usize;
+            let filled = if towards_end {
+                &cells[cells.len() - count..]
+            } else {
+                &cells[..count]
+            };
+            for &other in filled {
+                result[other as usize] += 1;
             }
         }
     }
-    result
 }
 
-fn move_rocks_west(input: &InputType) -> InputType {
-    let mut result = input.clone();
-    for row in 0..input.len() {
-        let mut free_col: Option<usize> = None;
-        for col in 0..input[0].len() {
-            match input[row][col] {
-                '.' => {
-                    if free_col.is_none() {
-                        free_col = Some(col);
-                    }
-                }
-                '#' => {
-                    free_col = None;
-                }
-                'O' => {
-                    if let Some(new_col) = free_col {
-                        result[row][new_col] = 'O';
-                        result[row][col] = '.';
-                        free_col = Some(new_col + 1);
+fn is_vertical(direction: Direction) -> bool {
+    matches!(direction, Direction::North | Direction::South)
+}
+
+// Whether tilting packs rocks against the end of their segments, the high bits of a line.
+fn is_towards_end(direction: Direction) -> bool {
+    matches!(direction, Direction::South | Direction::East)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum RoundRocks {
+    // One bitset per row, with bit `col` set for a rock, before the platform is tilted.
+    Scattered(Vec<u128>),
+    // The number of rocks in each segment after tilting in the direction, which packs
+    // them against one end of their segments.
+    Packed(Direction, Vec<u16>),
+}
+
+// The platform's cube rocks as one bitset per row, with bit `col` set when that cell
+// holds a rock. Bit 0 is the west edge, and platforms can be at most 128 cells across
+// in either direction. Once tilted, the round rocks are just counted per segment, and a
+// tilt works out the counts for the segments the other way with the precomputed tables.
+#[derive(Clone, Debug, PartialEq)]
+struct Platform {
+    width: usize,
+    cube: Vec<u128>,
+    round: RoundRocks,
+    rows: SegmentTable,
+    columns: SegmentTable,
+    // Space for the next counts, kept to save allocating on every tilt.
+    spare: Vec<u16>,
+}
+
+impl Platform {
+    fn from_grid(input: &InputType) -> Self {
+        let width = input[0].len();
+        assert!(width <= 128 && input.len() <= 128, "Platform too large");
+        let to_masks = |view: GridView<char>, rock: char| -> Vec<u128> {
+            (0..view.height())
+                .map(|row| {
+                    view.row(row)
+                        .enumerate()
+                        .filter(|(_, &c)| c == rock)
+                        .fold(0, |mask, (i, _)| mask | 1 << i)
+                })
+                .collect()
+        };
+        let view = GridView::new(input);
+        let cube = to_masks(view, '#');
+        let cube_columns = to_masks(view.transpose(), '#');
+        Self {
+            width,
+            round: RoundRocks::Scattered(to_masks(view, 'O')),
+            rows: SegmentTable::new(&cube, &cube_columns),
+            columns: SegmentTable::new(&cube_columns, &cube),
+            cube,
+            spare: Vec::new(),
+        }
+    }
+
+    fn table(&self, direction: Direction) -> &SegmentTable {
+        match direction {
+            Direction::North | Direction::South => &self.columns,
+            Direction::West | Direction::East => &self.rows,
+        }
+    }
+
+    // The round rocks as one bitset per row.
+    fn round_rows(&self) -> Vec<u128> {
+        match &self.round {
+            RoundRocks::Scattered(rows) => rows.clone(),
+            RoundRocks::Packed(direction, counts) => {
+                let towards_end = is_towards_end(*direction);
+                let mut rows = vec![0; self.cube.len()];
+                for (segment, &count) in self.table(*direction).segments.iter().zip(counts) {
+                    let packed = segment.packed(count as u32, towards_end);
+                    match direction {
+                        Direction::North | Direction::South => {
+                            for row in packed {
+                                rows[row as usize] |= 1 << segment.line;
+                            }
+                        }
+                        Direction::West | Direction::East => {
+                            rows[segment.line] |= low_bits(packed.end) & !low_bits(packed.start);
+                        }
                     }
                 }
-                _ => panic!("Invalid input"),
+                rows
             }
         }
     }
-    result
-}
 
-fn move_rocks_east(input: &InputType) -> InputType {
-    let mut result = input.clone();
-    for row in 0..input.len() {
-        let mut free_col: Option<usize> = None;
-        for col in (0..input.len()).rev() {
-            match input[row][col] {
-                '.' => {
-                    if free_col.is_none() {
-                        free_col = Some(col);
+    #[cfg(test)]
+    fn to_grid(&self) -> InputType {
+        self.round_rows()
+            .iter()
+            .zip(&self.cube)
+            .map(|(&round, &cube)| {
+                (0..self.width)
+                    .map(|col| match (round >> col & 1, cube >> col & 1) {
+                        (1, _) => 'O',
+                        (_, 1) => '#',
+                        _ => '.',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Rolls every round rock as far as it will go in the given direction.
+    fn tilt(&mut self, direction: Direction) {
+        let mut counts = std::mem::take(&mut self.spare);
+        match &self.round {
+            RoundRocks::Scattered(rows) => {
+                counts.clear();
+                counts.resize(self.table(direction).segments.len(), 0);
+                for (i, segment) in self.rows.segments.iter().enumerate() {
+                    let mut rocks = rows[segment.line] & segment.mask();
+                    if !is_vertical(direction) {
+                        counts[i] = rocks.count_ones() as u16;
+                        continue;
                     }
-                }
-                '#' => {
-                    free_col = None;
-                }
-                'O' => {
-                    if let Some(new_col) = free_col {
-                        result[row][new_col] = 'O';
-                        result[row][col] = '.';
-                        free_col = Some(new_col - 1);
+                    while rocks != 0 {
+                        let bit = rocks.trailing_zeros() - segment.start;
+                        counts[self.rows.crossing[self.rows.offsets[i] + bit as usize] as usize] +=
+                            1;
+                        rocks &= rocks - 1;
                     }
                 }
-                _ => panic!("Invalid input"),
             }
+            RoundRocks::Packed(packed, packed_counts) => {
+                if is_vertical(*packed) == is_vertical(direction) {
+                    // The rocks stay in the same segments.
+                    counts.clone_from(packed_counts);
+                } else {
+                    let table = self.table(*packed);
+                    table.count_crossing(packed_counts, is_towards_end(*packed), &mut counts);
+                }
+            }
+        }
+        let old = std::mem::replace(&mut self.round, RoundRocks::Packed(direction, counts));
+        if let RoundRocks::Packed(_, old_counts) = old {
+            self.spare = old_counts;
         }
     }
-    result
+
+    fn spin_cycle(&mut self) {
+        for &direction in &[
+            Direction::North,
+            Direction::West,
+            Direction::South,
+            Direction::East,
+        ] {
+            self.tilt(direction);
+        }
+    }
+
+    fn load(&self) -> SolutionType {
+        let height = self.cube.len();
+        self.round_rows()
+            .iter()
+            .enumerate()
+            .map(|(row, round)| round.count_ones() * (height - row) as SolutionType)
+            .sum()
+    }
 }
 
-fn run_cycle(input: &InputType) -> InputType {
-    let mut result = input.clone();
-    result = move_rocks_north(&result);
-    result = move_rocks_west(&result);
-    result = move_rocks_south(&result);
-    result = move_rocks_east(&result);
-    result
+fn solve_part1(input: &InputType) -> SolutionType {
+    let mut platform = Platform::from_grid(input);
+    platform.tilt(Direction::North);
+    platform.load()
 }
 
-fn calculate_load(input: &InputType) -> SolutionType {
-    let mut result = 0;
-    for (row_num, row) in input.iter().enumerate() {
-        for col in row {
-            if *col == 'O' {
-                result += input.len() - row_num;
-            }
+// The rocks soon settle into a loop, so only run cycles until a state repeats.
+fn spin(input: &InputType, cycles: usize) -> SolutionType {
+    let mut platform = Platform::from_grid(input);
+    let mut seen: HashMap<Vec<u128>, usize> = HashMap::new();
+    let mut history = Vec::new();
+    for cycle in 0..cycles {
+        let round = platform.round_rows();
+        if let Some(&loop_start) = seen.get(&round) {
+            let loop_length = cycle - loop_start;
+            return history[loop_start + (cycles - loop_start) % loop_length];
         }
+        seen.insert(round, cycle);
+        history.push(platform.load());
+        platform.spin_cycle();
     }
-    result as SolutionType
+    platform.load()
 }
 
-fn solve_part1(input: &InputType) -> SolutionType {
-    calculate_load(&move_rocks_north(input))
+fn solve_part2(input: &InputType) -> SolutionType {
+    spin(input, 1_000_000_000)
 }
 
-fn solve_part2(input: &InputType) -> SolutionType {
-    // TODO: Not performant.
-    let mut result = input.clone();
-    for i in 0..1_000_000_000 {
-        println!("Running cycle {}", i);
-        let result = run_cycle(&result);
+// How many spin cycles a second --bench expects to run.
+const TARGET_CYCLES_PER_SECOND: f64 = 1_000_000.0;
+
+// Runs spin cycles on the platform for about a second and returns how many ran per second.
+fn spin_cycles_per_second(input: &InputType) -> f64 {
+    let mut platform = Platform::from_grid(input);
+    let start = std::time::Instant::now();
+    let mut cycles = 0;
+    while start.elapsed().as_secs_f64() < 1.0 {
+        for _ in 0..1000 {
+            platform.spin_cycle();
+        }
+        cycles += 1000;
     }
-    calculate_load(&result)
+    cycles as f64 / start.elapsed().as_secs_f64()
 }
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("sample_input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
+    // With --bench, measure how fast spin cycles run instead of solving, and fail if
+    // they are slower than the target.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--bench") {
+        let rate = spin_cycles_per_second(&input);
+        println!(
+            "Spin cycles: {:.0} per second (target {:.0})",
+            rate, TARGET_CYCLES_PER_SECOND
+        );
+        if rate < TARGET_CYCLES_PER_SECOND {
+            println!("Below target");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let part1_start = std::time::Instant::now();
     let part1 = solve_part1(&input);
     println!("Part 1: {} ({:?})", part1, part1_start.elapsed());
@@ -186,8 +393,13 @@ fn main() {
     println!("Part 2: {} ({:?})", part2, part2_start.elapsed());
 }
 
+#[cfg(test)]
+#[path = "../common/rng.rs"]
+mod rng;
+
 #[cfg(test)]
 mod tests {
+    use super::rng::Rng;
     use super::*;
 
     const SAMPLE_INPUT: &str = include_str!("sample_input.txt");
@@ -195,36 +407,150 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.len(), 10);
         assert_eq!(input[0].len(), 10);
     }
 
+    const SAMPLE_CYCLES: [&str; 3] = [
+        ".....#....\n....#...O#\n...OO##...\n.OO#......\n.....OOO#.\n\
+         .O#...O#.#\n....O#....\n......OOOO\n#...O###..\n#..OO#....",
+        ".....#....\n....#...O#\n.....##...\n..O#......\n.....OOO#.\n\
+         .O#...O#.#\n....O#...O\n.......OOO\n#..OO###..\n#.OOO#...O",
+        ".....#....\n....#...O#\n.....##...\n..O#......\n.....OOO#.\n\
+         .O#...O#.#\n....O#...O\n.......OOO\n#...O###.O\n#.OOO#...O",
+    ];
+
     #[test]
-    fn test_move_rocks_north() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        let expected = parse_input(SAMPLE_EXPECTED.to_string());
-        let result = move_rocks_north(&input);
-        assert_eq!(result, expected);
+    fn test_find_segments() {
+        // .#..##. and a line with no cube rocks.
+        let segments = find_segments(&[0b0110010, 0], 7);
+        let ranges: Vec<(usize, u32, u32)> = segments
+            .iter()
+            .map(|segment| (segment.line, segment.start, segment.end))
+            .collect();
+        assert_eq!(ranges, vec![(0, 0, 1), (0, 2, 4), (0, 6, 7), (1, 0, 7)]);
+        assert_eq!(segments[1].mask(), 0b0001100);
+        assert_eq!(find_segments(&[u128::MAX], 128), vec![]);
+        assert_eq!(find_segments(&[0], 128)[0].mask(), u128::MAX);
     }
 
     #[test]
-    fn test_calculate_load() {
-        let input = parse_input(SAMPLE_EXPECTED.to_string());
-        let result = calculate_load(&input);
-        assert_eq!(result, 136);
+    fn test_segment_table() {
+        // .#
+        // ..
+        let platform = Platform::from_grid(&parse_input(".#\n.."));
+        assert_eq!(platform.rows.crossing, vec![0, 0, 1]);
+        assert_eq!(platform.columns.crossing, vec![0, 1, 1]);
+        let mut counts = Vec::new();
+        platform.rows.count_crossing(&[1, 2], false, &mut counts);
+        assert_eq!(counts, vec![2, 1]);
+        platform.rows.count_crossing(&[0, 1], true, &mut counts);
+        assert_eq!(counts, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_tilt_north() {
+        let input = parse_input(SAMPLE_INPUT);
+        let expected = parse_input(SAMPLE_EXPECTED);
+        let mut platform = Platform::from_grid(&input);
+        platform.tilt(Direction::North);
+        assert_eq!(platform.to_grid(), expected);
+    }
+
+    #[test]
+    fn test_spin_cycle() {
+        let input = parse_input(SAMPLE_INPUT);
+        let mut platform = Platform::from_grid(&input);
+        for expected in SAMPLE_CYCLES.iter() {
+            platform.spin_cycle();
+            assert_eq!(platform.to_grid(), parse_input(expected));
+        }
+    }
+
+    // Moves one rock at a time north until nothing can move, as a reference for the bitwise tilt.
+    fn tilt_north_slowly(grid: &mut InputType) {
+        let mut moved = true;
+        while moved {
+            moved = false;
+            for row in 1..grid.len() {
+                for col in 0..grid[0].len() {
+                    if grid[row][col] == 'O' && grid[row - 1][col] == '.' {
+                        grid[row - 1][col] = 'O';
+                        grid[row][col] = '.';
+                        moved = true;
+                    }
+                }
+            }
+        }
+    }
+
+    // Turns the grid so the given direction faces north, tilts it, and turns it back.
+    fn tilt_slowly(grid: &InputType, direction: Direction) -> InputType {
+        let view = GridView::new(grid);
+        let mut turned = match direction {
+            Direction::North => view,
+            Direction::East => view.rotate_counter_clockwise(),
+            Direction::South => view.rotate_180(),
+            Direction::West => view.rotate_clockwise(),
+        }
+        .to_grid();
+        tilt_north_slowly(&mut turned);
+        let view = GridView::new(&turned);
+        match direction {
+            Direction::North => view,
+            Direction::East => view.rotate_clockwise(),
+            Direction::South => view.rotate_180(),
+            Direction::West => view.rotate_counter_clockwise(),
+        }
+        .to_grid()
+    }
+
+    #[test]
+    fn test_tilt_matches_reference() {
+        let mut rng = Rng::new(0x2545_f491_4f6c_dd1d);
+        let directions = [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ];
+        for _ in 0..200 {
+            let height = 1 + rng.below(128) as usize;
+            let width = 1 + rng.below(128) as usize;
+            let mut grid: InputType = (0..height)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| ['.', '.', 'O', '#'][rng.below(4) as usize])
+                        .collect()
+                })
+                .collect();
+            let mut platform = Platform::from_grid(&grid);
+            for _ in 0..8 {
+                let direction = directions[rng.below(4) as usize];
+                grid = tilt_slowly(&grid, direction);
+                platform.tilt(direction);
+                assert_eq!(platform.to_grid(), grid);
+            }
+        }
+    }
+
+    #[test]
+    fn test_load() {
+        let input = parse_input(SAMPLE_EXPECTED);
+        assert_eq!(Platform::from_grid(&input).load(), 136);
     }
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 136);
     }
 
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part2(&input);
         assert_eq!(result, 64);
     }
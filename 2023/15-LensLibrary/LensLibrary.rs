@@ -3,11 +3,11 @@
 
 use std::fs::read_to_string;
 
-type InputType = Vec<String>;
+type InputType<'a> = Vec<&'a str>;
 type SolutionType = u32;
 
-fn parse_input(input_str: String) -> InputType {
-    input_str.trim().split(',').map(|s| s.to_string()).collect()
+fn parse_input(input_str: &str) -> InputType<'_> {
+    input_str.trim().split(',').collect()
 }
 
 fn calculate_hash(s: &str) -> SolutionType {
@@ -21,7 +21,7 @@ fn calculate_hash(s: &str) -> SolutionType {
 }
 
 fn solve_part1(input: &InputType) -> SolutionType {
-    input.iter().map(|s| calculate_hash(s.as_str())).sum()
+    input.iter().map(|s| calculate_hash(s)).sum()
 }
 
 fn solve_part2(input: &InputType) -> SolutionType {
@@ -30,7 +30,8 @@ fn solve_part2(input: &InputType) -> SolutionType {
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
     let part1_start = std::time::Instant::now();
@@ -50,31 +51,23 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.len(), 11);
     }
 
     #[test]
     fn test_calculate_hash() {
         assert_eq!(calculate_hash("HASH"), 52);
+        assert_eq!(calculate_hash(parse_input(SAMPLE_INPUT)[0]), 30);
         assert_eq!(
-            calculate_hash(parse_input(SAMPLE_INPUT.to_string())[0].as_str()),
-            30
-        );
-        assert_eq!(
-            calculate_hash(
-                parse_input(SAMPLE_INPUT.to_string())
-                    .last()
-                    .unwrap()
-                    .as_str()
-            ),
+            calculate_hash(parse_input(SAMPLE_INPUT).last().unwrap()),
             231
         );
     }
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 1320)
     }
@@ -5,6 +5,10 @@ use std::collections::HashMap;
 use std::fs::read_to_string;
 use std::panic;
 
+#[path = "../common/grid.rs"]
+mod grid;
+use grid::GridView;
+
 type InputType = Grid;
 type SolutionType = u32;
 
@@ -20,6 +24,26 @@ enum Direction {
     Right,
 }
 
+impl Direction {
+    fn turn_left(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    fn turn_right(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Beam {
     location: Point,
@@ -68,15 +92,10 @@ impl Beam {
         Self::beam_in_direction(self.location, new_direction)
     }
 
-    fn split_beam_vertical(&self) -> [Beam; 2] {
-        let new_beam1 = Self::beam_in_direction(self.location, Direction::Up);
-        let new_beam2 = Self::beam_in_direction(self.location, Direction::Down);
-        [new_beam1, new_beam2]
-    }
-
-    fn split_beam_horizontal(&self) -> [Beam; 2] {
-        let new_beam1 = Self::beam_in_direction(self.location, Direction::Left);
-        let new_beam2 = Self::beam_in_direction(self.location, Direction::Right);
+    // A splitter sends the beam out both ways at right angles to where it was heading.
+    fn split_beam(&self) -> [Beam; 2] {
+        let new_beam1 = Self::beam_in_direction(self.location, self.direction.turn_left());
+        let new_beam2 = Self::beam_in_direction(self.location, self.direction.turn_right());
         [new_beam1, new_beam2]
     }
 }
@@ -91,7 +110,7 @@ fn print_grid(grid: &Grid) {
     }
 }
 
-fn parse_input(input_str: String) -> InputType {
+fn parse_input(input_str: &str) -> InputType {
     input_str
         .lines()
         .map(|line| line.chars().collect())
@@ -146,7 +165,7 @@ fn move_beam(grid: &Grid, beam: &Beam, energy_map: &mut EnergyMap) {
                 let updated_beam = beam.beam_in_current_direction();
                 move_beam(grid, &updated_beam, energy_map);
             } else {
-                let new_beams = beam.split_beam_vertical();
+                let new_beams = beam.split_beam();
                 move_beam(grid, &new_beams[0], energy_map);
                 move_beam(grid, &new_beams[1], energy_map);
             }
@@ -156,7 +175,7 @@ fn move_beam(grid: &Grid, beam: &Beam, energy_map: &mut EnergyMap) {
                 let updated_beam = beam.beam_in_current_direction();
                 move_beam(grid, &updated_beam, energy_map);
             } else {
-                let new_beams = beam.split_beam_horizontal();
+                let new_beams = beam.split_beam();
                 move_beam(grid, &new_beams[0], energy_map);
                 move_beam(grid, &new_beams[1], energy_map);
             }
@@ -179,20 +198,22 @@ fn make_point(row: usize, col: usize) -> Point {
     (row as i32, col as i32)
 }
 
+// Each rotation puts a different edge of the grid on the left of the view,
+// where beams enter heading right across the view.
 fn all_start_beams(grid: &InputType) -> Vec<Beam> {
+    let view = GridView::new(grid);
+    let edges = [
+        (view, Direction::Right),
+        (view.rotate_clockwise(), Direction::Up),
+        (view.rotate_180(), Direction::Left),
+        (view.rotate_counter_clockwise(), Direction::Down),
+    ];
     let mut beams = Vec::new();
-    let row_length = grid.len();
-    let column_length = grid[0].len();
-    for row in 0..row_length {
-        beams.push(Beam::new(make_point(row, 0), Direction::Right));
-        beams.push(Beam::new(
-            make_point(row, column_length - 1),
-            Direction::Left,
-        ));
-    }
-    for col in 0..column_length {
-        beams.push(Beam::new(make_point(0, col), Direction::Down));
-        beams.push(Beam::new(make_point(row_length - 1, col), Direction::Up));
+    for (view, direction) in edges.iter() {
+        for row in 0..view.height() {
+            let (grid_row, grid_col) = view.source(row, 0);
+            beams.push(Beam::new(make_point(grid_row, grid_col), *direction));
+        }
     }
     beams
 }
@@ -207,7 +228,8 @@ fn solve_part2(input: &InputType) -> SolutionType {
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
     let part1_start = std::time::Instant::now();
@@ -227,7 +249,7 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.len(), 10);
         assert_eq!(input[0].len(), 10);
     }
@@ -235,62 +257,77 @@ mod tests {
     #[test]
     fn test_move_beam() {
         let mut energy_map = HashMap::new();
-        let grid = parse_input(".....".to_string());
+        let grid = parse_input(".....");
         let beam = Beam::new((0, 0), Direction::Right);
         move_beam(&grid, &beam, &mut energy_map);
         assert_eq!(energy_map.len(), 5);
 
         energy_map.clear();
-        let grid = parse_input("/....".to_string());
+        let grid = parse_input("/....");
         move_beam(&grid, &beam, &mut energy_map);
         assert_eq!(energy_map.len(), 1);
 
         energy_map.clear();
-        let grid = parse_input("\\....".to_string());
+        let grid = parse_input("\\....");
         move_beam(&grid, &beam, &mut energy_map);
         assert_eq!(energy_map.len(), 1);
 
         energy_map.clear();
-        let grid = parse_input("|....".to_string());
+        let grid = parse_input("|....");
         move_beam(&grid, &beam, &mut energy_map);
         assert_eq!(energy_map.len(), 1);
 
         energy_map.clear();
-        let grid = parse_input("-....".to_string());
+        let grid = parse_input("-....");
         move_beam(&grid, &beam, &mut energy_map);
         assert_eq!(energy_map.len(), 5);
 
         energy_map.clear();
-        let grid = parse_input("\\\n.\n.\n.\n.".to_string());
+        let grid = parse_input("\\\n.\n.\n.\n.");
         move_beam(&grid, &beam, &mut energy_map);
         assert_eq!(energy_map.len(), 5);
 
         energy_map.clear();
-        let grid = parse_input("\\....\n../..\n\\./..\n.....\n".to_string());
+        let grid = parse_input("\\....\n../..\n\\./..\n.....\n");
         move_beam(&grid, &beam, &mut energy_map);
         assert_eq!(energy_map.len(), 8);
 
         energy_map.clear();
-        let grid = parse_input("\\....\n|....\n\\.|..\n.....\n.....\n".to_string());
+        let grid = parse_input("\\....\n|....\n\\.|..\n.....\n.....\n");
         move_beam(&grid, &beam, &mut energy_map);
         assert_eq!(energy_map.len(), 9);
 
         energy_map.clear();
-        let grid = parse_input("\\.-..\n|....\n\\-|..\n.....\n.....\n".to_string());
+        let grid = parse_input("\\.-..\n|....\n\\-|..\n.....\n.....\n");
         move_beam(&grid, &beam, &mut energy_map);
         assert_eq!(energy_map.len(), 12);
     }
 
+    #[test]
+    fn test_all_start_beams() {
+        let grid = parse_input("...\n...");
+        let beams: Vec<(Point, Direction)> = all_start_beams(&grid)
+            .iter()
+            .map(|beam| (beam.location, beam.direction))
+            .collect();
+        assert_eq!(beams.len(), 10);
+        assert!(beams.contains(&((1, 0), Direction::Right)));
+        assert!(beams.contains(&((1, 2), Direction::Up)));
+        assert!(beams.contains(&((0, 2), Direction::Left)));
+        assert!(beams.contains(&((0, 0), Direction::Down)));
+        assert!(!beams.contains(&((0, 1), Direction::Up)));
+    }
+
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 46)
     }
 
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part2(&input);
         assert_eq!(result, 51)
     }
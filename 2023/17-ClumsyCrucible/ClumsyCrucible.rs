@@ -3,14 +3,13 @@
 
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::fs::read_to_string;
 
 type InputType = Map;
 type SolutionType = u32;
 type Point = (i32, i32);
 type GridLocation = (usize, usize);
-type NextFunction = fn(&Crucible, Direction) -> Option<Crucible>;
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 enum Direction {
@@ -20,14 +19,84 @@ enum Direction {
     West,
 }
 
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
 impl Direction {
-    fn available_directions(&self) -> Vec<Direction> {
+    fn opposite(&self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    fn arrow(&self) -> char {
         match self {
-            Direction::North => vec![Direction::North, Direction::East, Direction::West],
-            Direction::East => vec![Direction::East, Direction::North, Direction::South],
-            Direction::South => vec![Direction::South, Direction::East, Direction::West],
-            Direction::West => vec![Direction::West, Direction::North, Direction::South],
+            Direction::North => '^',
+            Direction::East => '>',
+            Direction::South => 'v',
+            Direction::West => '<',
+        }
+    }
+}
+
+// How a crucible may move: once it starts in a direction it must go at least
+// `min_straight` blocks before turning, stopping or reversing, and at most `max_straight`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct MovementRules {
+    min_straight: usize,
+    max_straight: usize,
+    can_reverse: bool,
+}
+
+const CRUCIBLE: MovementRules = MovementRules {
+    min_straight: 1,
+    max_straight: 3,
+    can_reverse: false,
+};
+
+const ULTRA_CRUCIBLE: MovementRules = MovementRules {
+    min_straight: 4,
+    max_straight: 10,
+    can_reverse: false,
+};
+
+impl MovementRules {
+    fn can_stop(&self, crucible: &Crucible) -> bool {
+        crucible.direction_count >= self.min_straight
+    }
+
+    // The directions the crucible can move in next. Before its first move it can go any way.
+    fn directions(&self, crucible: &Crucible) -> Vec<Direction> {
+        if crucible.direction_count == 0 {
+            return DIRECTIONS.to_vec();
         }
+        DIRECTIONS
+            .iter()
+            .copied()
+            .filter(|&direction| {
+                if direction == crucible.direction {
+                    crucible.direction_count < self.max_straight
+                } else if direction == crucible.direction.opposite() {
+                    self.can_reverse && self.can_stop(crucible)
+                } else {
+                    self.can_stop(crucible)
+                }
+            })
+            .collect()
+    }
+
+    fn next_moves(&self, crucible: &Crucible) -> Vec<Crucible> {
+        self.directions(crucible)
+            .into_iter()
+            .map(|direction| crucible.moved(direction))
+            .collect()
     }
 }
 
@@ -47,12 +116,17 @@ impl Crucible {
         }
     }
 
-    fn neighbors(&self, next: NextFunction) -> Vec<Crucible> {
-        self.direction
-            .available_directions()
-            .iter()
-            .filter_map(|direction| next(self, *direction))
-            .collect()
+    fn moved(&self, direction: Direction) -> Crucible {
+        let direction_count = if direction == self.direction {
+            self.direction_count + 1
+        } else {
+            1
+        };
+        Crucible {
+            location: self.location_in_direction(direction),
+            direction,
+            direction_count,
+        }
     }
 
     fn location_in_direction(&self, direction: Direction) -> Point {
@@ -84,6 +158,13 @@ impl PartialOrd for Node {
     }
 }
 
+// The best route found, as every position of the crucible from the start to the end.
+#[derive(Debug)]
+struct CruciblePath {
+    heat_loss: SolutionType,
+    steps: Vec<Crucible>,
+}
+
 #[derive(Debug)]
 struct Map {
     grid: Vec<Vec<char>>,
@@ -108,56 +189,80 @@ impl Map {
         }
     }
 
-    #[allow(dead_code)]
-    fn pretty_print(&self) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                print!("{}", self.grid[y][x])
+    // Draws the map with the path marked by arrows showing which way the crucible went.
+    fn render_path(&self, path: &CruciblePath) -> String {
+        let mut grid = self.grid.clone();
+        for crucible in path.steps.iter().skip(1) {
+            let (x, y) = self.grid_location(crucible.location).unwrap();
+            grid[y][x] = crucible.direction.arrow();
+        }
+        grid.iter()
+            .map(|row| row.iter().collect::<String>() + "\n")
+            .collect()
+    }
+
+    // Prints the map with the path on it, or just the map if there is no path.
+    fn pretty_print(&self, path: Option<&CruciblePath>) {
+        match path {
+            Some(path) => print!("{}", self.render_path(path)),
+            None => {
+                for y in 0..self.height {
+                    for x in 0..self.width {
+                        print!("{}", self.grid[y][x])
+                    }
+                    println!()
+                }
             }
-            println!()
         }
     }
 
-    fn find_minimum_heat_loss(&self, next: NextFunction) -> SolutionType {
-        let mut minimum_heat_loss = SolutionType::MAX;
-        let mut visited: HashSet<Crucible> = HashSet::new();
+    // Dijkstra's algorithm over crucible states. Returns None if the rules never let the
+    // crucible stop at the end.
+    fn find_minimum_heat_loss(&self, rules: &MovementRules) -> Option<CruciblePath> {
+        let mut best: HashMap<Crucible, SolutionType> = HashMap::new();
+        let mut previous: HashMap<Crucible, Crucible> = HashMap::new();
 
         let mut open_list = BinaryHeap::new();
-        let start_point = point_for_location(self.start);
-        open_list.push(Node {
-            heat_loss: 0,
-            crucible: Crucible::new(start_point, Direction::East),
-        });
+        let start = Crucible::new(point_for_location(self.start), Direction::East);
+        best.insert(start, 0);
         open_list.push(Node {
             heat_loss: 0,
-            crucible: Crucible::new(start_point, Direction::South),
+            crucible: start,
         });
 
-        while !open_list.is_empty() {
-            let node = open_list.pop().unwrap();
-            for crucible in node.crucible.neighbors(next) {
+        while let Some(node) = open_list.pop() {
+            if node.heat_loss > best[&node.crucible] {
+                continue;
+            }
+            let location = self.grid_location(node.crucible.location).unwrap();
+            if location == self.end && rules.can_stop(&node.crucible) {
+                let mut steps = vec![node.crucible];
+                while let Some(&crucible) = previous.get(steps.last().unwrap()) {
+                    steps.push(crucible);
+                }
+                steps.reverse();
+                return Some(CruciblePath {
+                    heat_loss: node.heat_loss,
+                    steps,
+                });
+            }
+            for crucible in rules.next_moves(&node.crucible) {
                 let Some(cell) = self.grid_location(crucible.location) else {
                     continue;
                 };
-                if visited.contains(&crucible) {
-                    continue;
-                }
                 let heat_loss = node.heat_loss + self.heat_loss(cell);
-                if heat_loss >= minimum_heat_loss {
-                    continue;
-                }
-                if cell == self.end {
-                    minimum_heat_loss = heat_loss;
+                if best.get(&crucible).is_some_and(|&known| known <= heat_loss) {
                     continue;
                 }
-                visited.insert(crucible);
+                best.insert(crucible, heat_loss);
+                previous.insert(crucible, node.crucible);
                 open_list.push(Node {
                     heat_loss,
                     crucible,
                 });
             }
         }
-        minimum_heat_loss
+        None
     }
 
     fn grid_location(&self, point: Point) -> Option<GridLocation> {
@@ -180,7 +285,7 @@ fn point_for_location(location: GridLocation) -> Point {
     (location.0 as i32, location.1 as i32)
 }
 
-fn parse_input(input_str: String) -> InputType {
+fn parse_input(input_str: &str) -> InputType {
     Map::new(
         input_str
             .lines()
@@ -189,59 +294,40 @@ fn parse_input(input_str: String) -> InputType {
     )
 }
 
-fn next(crucible: &Crucible, direction: Direction) -> Option<Crucible> {
-    if crucible.direction != direction {
-        Some(Crucible {
-            location: crucible.location_in_direction(direction),
-            direction,
-            direction_count: 1,
-        })
-    } else if crucible.direction_count < 3 {
-        Some(Crucible {
-            location: crucible.location_in_direction(direction),
-            direction,
-            direction_count: crucible.direction_count + 1,
-        })
-    } else {
-        None
-    }
+fn solve(input: &InputType, rules: &MovementRules) -> CruciblePath {
+    input
+        .find_minimum_heat_loss(rules)
+        .expect("The crucible cannot reach the end")
 }
 
 fn solve_part1(input: &InputType) -> SolutionType {
-    input.find_minimum_heat_loss(next)
-}
-
-fn next_ultra(crucible: &Crucible, direction: Direction) -> Option<Crucible> {
-    if crucible.direction != direction {
-        if crucible.direction_count >= 4 {
-            Some(Crucible {
-                location: crucible.location_in_direction(direction),
-                direction,
-                direction_count: 1,
-            })
-        } else {
-            None
-        }
-    } else if crucible.direction_count < 10 {
-        Some(Crucible {
-            location: crucible.location_in_direction(direction),
-            direction,
-            direction_count: crucible.direction_count + 1,
-        })
-    } else {
-        None
-    }
+    solve(input, &CRUCIBLE).heat_loss
 }
 
 fn solve_part2(input: &InputType) -> SolutionType {
-    input.find_minimum_heat_loss(next_ultra)
+    solve(input, &ULTRA_CRUCIBLE).heat_loss
 }
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
+    // With --path, draw the best path for each kind of crucible instead of solving.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--path") {
+        for (name, rules) in [("Crucible", &CRUCIBLE), ("Ultra crucible", &ULTRA_CRUCIBLE)] {
+            let path = input.find_minimum_heat_loss(rules);
+            match &path {
+                Some(path) => println!("{}: heat loss {}", name, path.heat_loss),
+                None => println!("{}: cannot reach the end", name),
+            }
+            input.pretty_print(path.as_ref());
+        }
+        return;
+    }
+
     let part1_start = std::time::Instant::now();
     let part1 = solve_part1(&input);
     println!("Part 1: {} ({:?})", part1, part1_start.elapsed());
@@ -259,7 +345,7 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let map = parse_input(SAMPLE_INPUT.to_string());
+        let map = parse_input(SAMPLE_INPUT);
         assert_eq!(map.grid.len(), 13);
         assert_eq!(map.grid[0].len(), 13);
         assert_eq!(map.width, 13);
@@ -270,14 +356,89 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 102)
     }
 
+    // Checks the path is one the rules allow, and adds up its heat loss.
+    fn check_path(map: &Map, path: &CruciblePath, rules: &MovementRules) {
+        assert_eq!(path.steps[0].location, (0, 0));
+        let last = path.steps.last().unwrap();
+        assert_eq!(map.grid_location(last.location), Some(map.end));
+        assert!(rules.can_stop(last));
+        for pair in path.steps.windows(2) {
+            assert!(rules.next_moves(&pair[0]).contains(&pair[1]));
+        }
+        let heat_loss: SolutionType = path.steps[1..]
+            .iter()
+            .map(|crucible| map.heat_loss(map.grid_location(crucible.location).unwrap()))
+            .sum();
+        assert_eq!(heat_loss, path.heat_loss);
+    }
+
+    #[test]
+    fn test_paths() {
+        let map = parse_input(SAMPLE_INPUT);
+        for rules in [CRUCIBLE, ULTRA_CRUCIBLE].iter() {
+            check_path(&map, &map.find_minimum_heat_loss(rules).unwrap(), rules);
+        }
+        let rules = MovementRules {
+            min_straight: 2,
+            max_straight: 5,
+            can_reverse: true,
+        };
+        check_path(&map, &map.find_minimum_heat_loss(&rules).unwrap(), &rules);
+    }
+
+    #[test]
+    fn test_render_path() {
+        let map = parse_input("112\n991\n991");
+        let path = map.find_minimum_heat_loss(&CRUCIBLE).unwrap();
+        assert_eq!(path.heat_loss, 5);
+        assert_eq!(map.render_path(&path), "1>>\n99v\n99v\n");
+    }
+
+    #[test]
+    fn test_unconstrained() {
+        // With no limits the answer is the plain shortest path, which here has to double
+        // back through the walls of 9s.
+        let map = parse_input("11111\n99991\n11111\n19999\n11111");
+        let rules = MovementRules {
+            min_straight: 1,
+            max_straight: usize::MAX,
+            can_reverse: true,
+        };
+        let path = map.find_minimum_heat_loss(&rules).unwrap();
+        assert_eq!(path.heat_loss, 16);
+        assert_eq!(path.steps.len(), 17);
+        // Limited to three blocks at a time, it must pay to cut through a 9.
+        let path = map.find_minimum_heat_loss(&CRUCIBLE).unwrap();
+        assert!(path.heat_loss > 16);
+        check_path(&map, &path, &CRUCIBLE);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let map = parse_input(SAMPLE_INPUT);
+        let rules = MovementRules {
+            min_straight: 20,
+            max_straight: 30,
+            can_reverse: false,
+        };
+        assert!(map.find_minimum_heat_loss(&rules).is_none());
+    }
+
+    #[test]
+    fn test_ultra_crucible_must_stop_straight() {
+        let map =
+            parse_input("111111111111\n999999999991\n999999999991\n999999999991\n999999999991");
+        assert_eq!(solve_part2(&map), 71);
+    }
+
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part2(&input);
         assert_eq!(result, 94)
     }
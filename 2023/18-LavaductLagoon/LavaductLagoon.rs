@@ -6,15 +6,15 @@ use std::fs::read_to_string;
 use std::ops::Range;
 use std::panic;
 
-type InputType = Vec<Line>;
+type InputType<'a> = Vec<Line<'a>>;
 type SolutionType = i64;
 type Point = (i32, i32);
 
 #[derive(Debug)]
-struct Line {
+struct Line<'a> {
     direction: char,
     distance: i32,
-    color: String,
+    color: &'a str,
 }
 
 #[derive(Debug)]
@@ -118,18 +118,14 @@ impl Lagoon {
     }
 }
 
-fn parse_input(input_str: String) -> InputType {
+fn parse_input(input_str: &str) -> InputType<'_> {
     input_str
         .lines()
         .map(|line| {
             let mut parts = line.split_whitespace();
             let direction = parts.next().unwrap().chars().next().unwrap();
             let distance = parts.next().unwrap().parse::<i32>().unwrap();
-            let color = parts
-                .next()
-                .unwrap()
-                .trim_matches(|c| c == '(' || c == ')')
-                .to_string();
+            let color = parts.next().unwrap().trim_matches(|c| c == '(' || c == ')');
             Line {
                 direction,
                 distance,
@@ -165,7 +161,7 @@ fn solve_part2(input: &InputType) -> SolutionType {
     // TODO: Not performant. Runs for hours without finding solution.
     let mut lagoon = Lagoon::new();
     for line in input {
-        let (direction, distance) = decode_hex(&line.color);
+        let (direction, distance) = decode_hex(line.color);
         lagoon.dig(direction, distance);
     }
     lagoon.fill();
@@ -174,7 +170,8 @@ fn solve_part2(input: &InputType) -> SolutionType {
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
     let part1_start = std::time::Instant::now();
@@ -194,7 +191,7 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.len(), 14);
         assert_eq!(input[0].direction, 'R');
         assert_eq!(input[0].distance, 6);
@@ -327,14 +324,14 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 62);
     }
 
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part2(&input);
         assert_eq!(result, 952408144115);
     }
@@ -1,247 +1,671 @@
 // Aplenty
 // https://adventofcode.com/2023/day/19
 
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
 use std::fs::read_to_string;
 use std::ops::Range;
 use std::panic;
 
-type InputType = (Workflows, Vec<Part>);
+#[path = "../common/parser.rs"]
+mod parser;
+use parser::{
+    character, delimited, either, identifier, literal, map, map_result, pair, parse_all,
+    separated1, terminated, unsigned, Parser,
+};
+
+#[path = "../common/sections.rs"]
+mod sections;
+use sections::{parse_section, parse_section_lines, section_lines};
+
+#[path = "../common/range_set.rs"]
+mod range_set;
+use range_set::RangeSet;
+
+type InputType<'a> = (Workflows<'a>, Vec<Part>);
 type SolutionType = i32;
-type Workflows = HashMap<String, Vec<Rule>>;
-type CategoryRanges = HashMap<char, Range<i32>>;
+type Workflows<'a> = HashMap<&'a str, Vec<Rule<'a>>>;
+type NodeId = usize;
+type RuleId = (usize, usize);
 
-#[derive(Debug)]
+// Every rating of a part lies in this range.
+const RATINGS: Range<i32> = 1..4001;
+
+// A part's rating in each category it is rated in.
+#[derive(Debug, PartialEq)]
 struct Part {
-    x: i32,
-    m: i32,
-    a: i32,
-    s: i32,
+    ratings: BTreeMap<char, i32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operation {
+    Less,
+    Greater,
+}
+
+impl Operation {
+    fn holds(self, rating: i32, value: i32) -> bool {
+        match self {
+            Operation::Less => rating < value,
+            Operation::Greater => rating > value,
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operation::Less => write!(f, "<"),
+            Operation::Greater => write!(f, ">"),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 struct Conditional {
     category: char,
-    operation: char,
+    operation: Operation,
     value: i32,
 }
 
-impl Conditional {
-    fn from_str(input: &str) -> Self {
-        let mut chars = input.chars();
-        let category = chars.next().unwrap();
-        let operation = chars.next().unwrap();
-        let value = chars
-            .by_ref()
-            .take_while(|c| c.is_ascii_digit())
-            .collect::<String>()
-            .parse::<i32>()
-            .unwrap();
-        Self {
+// a<2006
+fn conditional<'a>() -> impl Parser<'a, Conditional> {
+    let category = character(|c| c.is_ascii_lowercase(), "category");
+    let operation = map(character(|c| c == '<' || c == '>', "'<' or '>'"), |c| {
+        if c == '<' {
+            Operation::Less
+        } else {
+            Operation::Greater
+        }
+    });
+    map(
+        pair(pair(category, operation), unsigned::<i32>()),
+        |((category, operation), value)| Conditional {
             category,
             operation,
             value,
-        }
+        },
+    )
+}
+
+impl fmt::Display for Conditional {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}{}", self.category, self.operation, self.value)
     }
 }
 
 #[derive(Clone, Debug)]
-struct Rule {
+struct Rule<'a> {
     conditional: Option<Conditional>,
-    when_true: String,
+    when_true: &'a str,
 }
 
-impl Rule {
-    fn from_str(input: &str) -> Self {
-        let mut parts = input.split(':').collect::<Vec<&str>>();
-        let conditional = if parts.len() > 1 {
-            Some(Conditional::from_str(parts.remove(0)))
-        } else {
-            None
-        };
-        let when_true = parts[0].to_string();
-        Self {
-            conditional,
+// a<2006:qkq or just A
+fn rule<'a>() -> impl Parser<'a, Rule<'a>> {
+    let conditional_rule = map(
+        pair(terminated(conditional(), literal(":")), identifier()),
+        |(conditional, when_true)| Rule {
+            conditional: Some(conditional),
             when_true,
+        },
+    );
+    let always_rule = map(identifier(), |when_true| Rule {
+        conditional: None,
+        when_true,
+    });
+    either(conditional_rule, always_rule)
+}
+
+// px{a<2006:qkq,m>2090:A,rfg}
+fn workflow<'a>() -> impl Parser<'a, (&'a str, Vec<Rule<'a>>)> {
+    let rules = delimited(literal("{"), separated1(rule(), literal(",")), literal("}"));
+    pair(identifier(), rules)
+}
+
+fn parse_workflow(line: &str) -> (&str, Vec<Rule<'_>>) {
+    parse_all(workflow(), line).unwrap_or_else(|error| panic!("{}", error))
+}
+
+fn parse_workflows(section: &str) -> Workflows<'_> {
+    section_lines(section).map(parse_workflow).collect()
+}
+
+// {x=787,m=2655,a=1222,s=2876}
+fn part<'a>() -> impl Parser<'a, Part> {
+    let category = character(|c| c.is_ascii_lowercase(), "category");
+    let rating = pair(terminated(category, literal("=")), unsigned::<i32>());
+    let ratings = delimited(literal("{"), separated1(rating, literal(",")), literal("}"));
+    map_result(ratings, |ratings| {
+        let count = ratings.len();
+        let ratings: BTreeMap<char, i32> = ratings.into_iter().collect();
+        if ratings.len() < count {
+            return Err("at most one rating per category".to_string());
+        }
+        Ok(Part { ratings })
+    })
+}
+
+fn parse_part(line: &str) -> Part {
+    parse_all(part(), line).unwrap_or_else(|error| panic!("{}", error))
+}
+
+fn parse_input(input_str: &str) -> InputType<'_> {
+    let workflows = parse_section(input_str, 0, parse_workflows);
+    let parts: Vec<Part> = parse_section_lines(input_str, 1, parse_part);
+    if let Some(first) = parts.first() {
+        for (index, part) in parts.iter().enumerate() {
+            assert!(
+                part.ratings.keys().eq(first.ratings.keys()),
+                "Part {} is not rated in the same categories as part 1",
+                index + 1
+            );
         }
     }
+    (workflows, parts)
 }
 
-fn parse_workflow(line: &str) -> (String, Vec<Rule>) {
-    let mut parts = line.split('{').collect::<Vec<&str>>();
-    let key = parts.remove(0).to_string();
-    let rules = parts
-        .remove(0)
-        .trim_end_matches('}')
-        .split(',')
-        .map(Rule::from_str)
-        .collect::<Vec<Rule>>();
-    (key, rules)
+// A part that is not rated in the tested category never matches.
+fn test_conditional(part: &Part, conditional: &Conditional) -> bool {
+    part.ratings
+        .get(&conditional.category)
+        .is_some_and(|&rating| conditional.operation.holds(rating, conditional.value))
 }
 
-fn parse_workflows(input: &[String]) -> Workflows {
-    input
-        .iter()
-        .map(|line| parse_workflow(line))
-        .collect::<Workflows>()
+const ACCEPT: NodeId = 0;
+const REJECT: NodeId = 1;
+
+#[derive(Debug)]
+enum Node {
+    Accept,
+    Reject,
+    Test {
+        conditional: Conditional,
+        // The index of the tested category, which is the dimension of the boxes it splits.
+        dimension: usize,
+        rule: RuleId,
+        if_true: NodeId,
+        if_false: NodeId,
+    },
 }
 
-fn parse_category(category: &str) -> i32 {
-    category.split('=').collect::<Vec<&str>>()[1]
-        .parse::<i32>()
-        .unwrap()
+#[derive(Debug, PartialEq)]
+enum CompileError {
+    UnknownWorkflow(String),
+    // A workflow tests a category that parts are not rated in.
+    UnknownCategory(String, char),
+    MissingFallback(String),
+    Cycle(Vec<String>),
 }
 
-fn parse_part(line: &str) -> Part {
-    let line = line.trim_start_matches('{').trim_end_matches('}');
-    let parts = line.split(',').collect::<Vec<&str>>();
-    let x = parse_category(parts[0]);
-    let m = parse_category(parts[1]);
-    let a = parse_category(parts[2]);
-    let s = parse_category(parts[3]);
-    Part { x, m, a, s }
+// Workflows compiled into a tree of tests stored in a single arena.
+// Workflow names are interned so rules refer to them by index.
+#[derive(Debug)]
+struct DecisionTree {
+    nodes: Vec<Node>,
+    names: Vec<String>,
+    categories: Vec<char>,
+    start: NodeId,
+    unreachable_rules: Vec<(String, usize)>,
 }
 
-fn parse_parts(input: &[String]) -> Vec<Part> {
-    input.iter().map(|line| parse_part(line)).collect()
+struct Compiler<'a> {
+    workflows: &'a Workflows<'a>,
+    categories: &'a [char],
+    ids: HashMap<&'a str, usize>,
+    names: Vec<String>,
+    entries: Vec<Option<NodeId>>,
+    in_progress: Vec<usize>,
+    nodes: Vec<Node>,
 }
 
-fn to_string_vec(input: &str) -> Vec<String> {
-    input.lines().map(|l| l.to_string()).collect()
+impl<'a> Compiler<'a> {
+    fn intern(&mut self, name: &'a str) -> usize {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        let id = self.names.len();
+        self.ids.insert(name, id);
+        self.names.push(name.to_string());
+        self.entries.push(None);
+        id
+    }
+
+    fn compile_target(&mut self, name: &'a str) -> Result<NodeId, CompileError> {
+        match name {
+            "A" => Ok(ACCEPT),
+            "R" => Ok(REJECT),
+            _ => self.compile_workflow(name),
+        }
+    }
+
+    fn compile_workflow(&mut self, name: &'a str) -> Result<NodeId, CompileError> {
+        let workflows = self.workflows;
+        let Some(rules) = workflows.get(name) else {
+            return Err(CompileError::UnknownWorkflow(name.to_string()));
+        };
+        let id = self.intern(name);
+        if let Some(entry) = self.entries[id] {
+            return Ok(entry);
+        }
+        if let Some(index) = self.in_progress.iter().position(|w| *w == id) {
+            let mut cycle: Vec<String> = self.in_progress[index..]
+                .iter()
+                .map(|w| self.names[*w].clone())
+                .collect();
+            cycle.push(name.to_string());
+            return Err(CompileError::Cycle(cycle));
+        }
+
+        self.in_progress.push(id);
+        // Build the chain of tests from the last rule back to the first
+        // so each test knows where to go when its condition is false.
+        let mut next: Option<NodeId> = None;
+        for (index, rule) in rules.iter().enumerate().rev() {
+            let target = self.compile_target(rule.when_true)?;
+            next = match (&rule.conditional, next) {
+                (None, _) => Some(target),
+                (Some(conditional), Some(if_false)) => {
+                    let Some(dimension) = self
+                        .categories
+                        .iter()
+                        .position(|c| *c == conditional.category)
+                    else {
+                        return Err(CompileError::UnknownCategory(
+                            name.to_string(),
+                            conditional.category,
+                        ));
+                    };
+                    self.nodes.push(Node::Test {
+                        conditional: conditional.clone(),
+                        dimension,
+                        rule: (id, index),
+                        if_true: target,
+                        if_false,
+                    });
+                    Some(self.nodes.len() - 1)
+                }
+                (Some(_), None) => return Err(CompileError::MissingFallback(name.to_string())),
+            };
+        }
+        self.in_progress.pop();
+
+        let entry = next.ok_or_else(|| CompileError::MissingFallback(name.to_string()))?;
+        self.entries[id] = Some(entry);
+        Ok(entry)
+    }
 }
 
-fn parse_input(input_str: String) -> InputType {
-    let mut parts = input_str.split("\n\n");
-    let workflows = parse_workflows(&to_string_vec(parts.next().unwrap()));
-    let parts = parse_parts(&to_string_vec(parts.next().unwrap()));
-    (workflows, parts)
+impl DecisionTree {
+    // Parts are rated in `categories`, each rating lying in `ratings`. Rules that no such
+    // part can match are recorded in `unreachable_rules`.
+    fn compile(
+        workflows: &Workflows,
+        categories: &[char],
+        ratings: Range<i32>,
+    ) -> Result<Self, CompileError> {
+        let mut compiler = Compiler {
+            workflows,
+            categories,
+            ids: HashMap::new(),
+            names: Vec::new(),
+            entries: Vec::new(),
+            in_progress: Vec::new(),
+            nodes: vec![Node::Accept, Node::Reject],
+        };
+        let start = compiler.compile_workflow("in")?;
+        Ok(Self {
+            nodes: compiler.nodes,
+            names: compiler.names,
+            categories: categories.to_vec(),
+            start,
+            unreachable_rules: find_unreachable_rules(workflows, categories, ratings),
+        })
+    }
+
+    fn is_accepted(&self, part: &Part) -> bool {
+        let mut node = self.start;
+        loop {
+            match &self.nodes[node] {
+                Node::Accept => return true,
+                Node::Reject => return false,
+                Node::Test {
+                    conditional,
+                    if_true,
+                    if_false,
+                    ..
+                } => {
+                    node = if test_conditional(part, conditional) {
+                        *if_true
+                    } else {
+                        *if_false
+                    };
+                }
+            }
+        }
+    }
+
+    // Returns the disjoint boxes of ratings within `start` that are accepted. The box has
+    // one dimension for each of the tree's categories.
+    fn accepted_boxes(&self, start: HyperBox) -> Vec<HyperBox> {
+        let mut accepted = Vec::new();
+        let mut stack = vec![(self.start, start)];
+        while let Some((node, hyper_box)) = stack.pop() {
+            if hyper_box.is_empty() {
+                continue;
+            }
+            match &self.nodes[node] {
+                Node::Accept => accepted.push(hyper_box),
+                Node::Reject => {}
+                Node::Test {
+                    conditional,
+                    dimension,
+                    if_true,
+                    if_false,
+                    ..
+                } => {
+                    let (true_box, false_box) =
+                        hyper_box.split(*dimension, conditional.operation, conditional.value);
+                    stack.push((*if_false, false_box));
+                    stack.push((*if_true, true_box));
+                }
+            }
+        }
+        accepted
+    }
+
+    fn write_node(&self, f: &mut fmt::Formatter, node: NodeId, depth: usize) -> fmt::Result {
+        match &self.nodes[node] {
+            Node::Accept => writeln!(f, "A"),
+            Node::Reject => writeln!(f, "R"),
+            Node::Test {
+                conditional,
+                rule,
+                if_true,
+                if_false,
+                ..
+            } => {
+                if rule.1 == 0 {
+                    write!(f, "{}: ", self.names[rule.0])?;
+                }
+                writeln!(f, "{}", conditional)?;
+                let indent = "  ".repeat(depth + 1);
+                write!(f, "{}true -> ", indent)?;
+                self.write_node(f, *if_true, depth + 1)?;
+                write!(f, "{}false -> ", indent)?;
+                self.write_node(f, *if_false, depth + 1)
+            }
+        }
+    }
 }
 
-fn test_conditional(part: &Part, conditional: &Conditional) -> bool {
-    let value = match conditional.category {
-        'x' => part.x,
-        'm' => part.m,
-        'a' => part.a,
-        's' => part.s,
-        _ => panic!("Unknown category"),
-    };
-    match conditional.operation {
-        '<' => value < conditional.value,
-        '>' => value > conditional.value,
-        _ => panic!("Unknown operation"),
+impl fmt::Display for DecisionTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_node(f, self.start, 0)
     }
 }
 
-fn is_part_accepted(part: &Part, workflows: &Workflows) -> bool {
-    let mut rules = workflows.get("in").unwrap();
-    loop {
-        for rule in rules {
-            let result = if rule.conditional.is_some() {
-                test_conditional(part, rule.conditional.as_ref().unwrap())
-            } else {
-                true
-            };
-            if result {
-                match rule.when_true.as_str() {
-                    "A" => return true,
-                    "R" => return false,
-                    _ => {
-                        rules = workflows.get(&rule.when_true).unwrap();
+// An axis-aligned box of part ratings with one set of values per category.
+#[derive(Clone, Debug, PartialEq)]
+struct HyperBox {
+    ranges: Vec<RangeSet<i32>>,
+}
+
+impl HyperBox {
+    fn new(dimensions: usize, range: Range<i32>) -> Self {
+        Self {
+            ranges: vec![RangeSet::from(range); dimensions],
+        }
+    }
+
+    fn volume(&self) -> u64 {
+        self.ranges.iter().map(|r| r.len() as u64).product()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ranges.iter().any(RangeSet::is_empty)
+    }
+
+    // Splits the box into the part where the condition holds and the part where it does not.
+    fn split(&self, dimension: usize, operation: Operation, value: i32) -> (Self, Self) {
+        let (when_true, when_false) = match operation {
+            Operation::Less => self.ranges[dimension].split_at(value),
+            Operation::Greater => {
+                let (below, above) = self.ranges[dimension].split_at(value + 1);
+                (above, below)
+            }
+        };
+        let mut true_box = self.clone();
+        let mut false_box = self.clone();
+        true_box.ranges[dimension] = when_true;
+        false_box.ranges[dimension] = when_false;
+        (true_box, false_box)
+    }
+}
+
+// Returns every category tested by the workflows in sorted order.
+fn tested_categories(workflows: &Workflows) -> Vec<char> {
+    workflows
+        .values()
+        .flatten()
+        .filter_map(|rule| rule.conditional.as_ref())
+        .map(|conditional| conditional.category)
+        .collect::<BTreeSet<char>>()
+        .into_iter()
+        .collect()
+}
+
+// Returns the categories parts are rated in, in sorted order. These are the dimensions
+// of the boxes used to count combinations, so a category no rule tests still multiplies
+// the count by the number of ratings. Without parts, only the tested categories are known.
+fn find_categories(input: &InputType) -> Vec<char> {
+    match input.1.first() {
+        Some(part) => part.ratings.keys().copied().collect(),
+        None => tested_categories(&input.0),
+    }
+}
+
+fn category_index(categories: &[char], category: char) -> usize {
+    categories
+        .iter()
+        .position(|c| *c == category)
+        .expect("Unknown category")
+}
+
+fn split_box(
+    categories: &[char],
+    conditional: &Conditional,
+    hyper_box: &HyperBox,
+) -> (HyperBox, HyperBox) {
+    hyper_box.split(
+        category_index(categories, conditional.category),
+        conditional.operation,
+        conditional.value,
+    )
+}
+
+// Adds `name` and every workflow it sends parts to, directly or indirectly, after the
+// workflows they send parts to.
+fn visit_workflow<'a>(
+    workflows: &'a Workflows<'a>,
+    name: &'a str,
+    visited: &mut HashSet<&'a str>,
+    order: &mut Vec<&'a str>,
+) {
+    if !visited.insert(name) {
+        return;
+    }
+    for rule in &workflows[name] {
+        if workflows.contains_key(rule.when_true) {
+            visit_workflow(workflows, rule.when_true, visited, order);
+        }
+    }
+    order.push(name);
+}
+
+// Returns every workflow ordered so that each comes before the workflows it sends parts to.
+fn topological_order<'a>(workflows: &'a Workflows<'a>) -> Vec<&'a str> {
+    let mut names: Vec<&str> = workflows.keys().copied().collect();
+    names.sort();
+    let mut visited = HashSet::new();
+    let mut order = Vec::new();
+    for name in names {
+        visit_workflow(workflows, name, &mut visited, &mut order);
+    }
+    order.reverse();
+    order
+}
+
+// Returns the workflow name and index of every rule that no part with ratings in the
+// given range can match. Each workflow is visited once with all the boxes of ratings
+// that can reach it. Must only be called on workflows that are known to be acyclic and
+// only test the given categories.
+fn find_unreachable_rules(
+    workflows: &Workflows,
+    categories: &[char],
+    ratings: Range<i32>,
+) -> Vec<(String, usize)> {
+    let mut arriving: HashMap<&str, Vec<HyperBox>> = HashMap::new();
+    arriving.insert("in", vec![HyperBox::new(categories.len(), ratings)]);
+    let mut unreachable = Vec::new();
+    for name in topological_order(workflows) {
+        let mut boxes = arriving.remove(name).unwrap_or_default();
+        for (index, rule) in workflows[name].iter().enumerate() {
+            let mut matched = Vec::new();
+            let mut unmatched = Vec::new();
+            for hyper_box in boxes {
+                match rule.conditional {
+                    Some(ref conditional) => {
+                        let (true_box, false_box) = split_box(categories, conditional, &hyper_box);
+                        matched.push(true_box);
+                        unmatched.push(false_box);
                     }
+                    None => matched.push(hyper_box),
                 }
-                break;
+            }
+            matched.retain(|hyper_box| !hyper_box.is_empty());
+            unmatched.retain(|hyper_box| !hyper_box.is_empty());
+            boxes = unmatched;
+            if matched.is_empty() {
+                unreachable.push((name.to_string(), index));
+            } else {
+                arriving.entry(rule.when_true).or_default().extend(matched);
             }
         }
     }
+    unreachable.sort();
+    unreachable
+}
+
+// Returns the workflow name and index of each rule the part follows from "in".
+fn find_part_path<'a>(part: &Part, workflows: &'a Workflows<'a>) -> Vec<(&'a str, usize)> {
+    let mut path = Vec::new();
+    let mut name = "in";
+    while name != "A" && name != "R" {
+        let (index, rule) = workflows[name]
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| match rule.conditional {
+                Some(ref conditional) => test_conditional(part, conditional),
+                None => true,
+            })
+            .expect("No rule matched");
+        path.push((name, index));
+        name = rule.when_true;
+    }
+    path
+}
+
+// Renders the workflows as a Graphviz digraph with one edge per rule.
+// If a part is given, the edges it follows are highlighted.
+fn workflows_to_dot(workflows: &Workflows, part: Option<&Part>) -> String {
+    let path: HashSet<(&str, usize)> = match part {
+        Some(part) => find_part_path(part, workflows).into_iter().collect(),
+        None => HashSet::new(),
+    };
+    let mut names: Vec<&str> = workflows.keys().copied().collect();
+    names.sort();
+
+    let mut dot = String::from("digraph workflows {\n");
+    dot.push_str("  in [style=filled, fillcolor=lightblue];\n");
+    dot.push_str("  A [shape=doublecircle, style=filled, fillcolor=palegreen];\n");
+    dot.push_str("  R [shape=doublecircle, style=filled, fillcolor=lightcoral];\n");
+    for name in names {
+        for (index, rule) in workflows[name].iter().enumerate() {
+            let label = match rule.conditional {
+                Some(ref conditional) => conditional.to_string(),
+                None => "else".to_string(),
+            };
+            let style = if path.contains(&(name, index)) {
+                ", color=red, penwidth=2"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "  {} -> {} [label=\"{}\"{}];\n",
+                name, rule.when_true, label, style
+            ));
+        }
+    }
+    dot.push_str("}\n");
+    dot
 }
 
 fn part_value(part: &Part) -> SolutionType {
-    part.x + part.m + part.a + part.s
+    part.ratings.values().sum()
 }
 
-fn solve_part1(input: &InputType) -> SolutionType {
+fn solve_part1(input: &InputType, tree: &DecisionTree) -> SolutionType {
     input
         .1
         .iter()
-        .filter(|p| is_part_accepted(p, &input.0))
+        .filter(|p| tree.is_accepted(p))
         .map(part_value)
         .sum()
 }
 
-fn category_combinations(ranges: CategoryRanges) -> u64 {
-    ranges
-        .values()
-        .map(|r| r.len() as u64)
-        .product()
-}
-
-fn split_ranges(conditional: &Conditional, ranges: CategoryRanges) -> (CategoryRanges, CategoryRanges) {
-    let mut range1 = ranges.clone();
-    let mut range2 = ranges.clone();
-    if conditional.operation == '<' {
-        range1.insert(conditional.category, ranges[&conditional.category].start..conditional.value);
-        range2.insert(conditional.category, conditional.value..ranges[&conditional.category].end);
-        (range1, range2)
-    } else if conditional.operation == '>' {
-        range1.insert(conditional.category, conditional.value + 1..ranges[&conditional.category].end);
-        range2.insert(conditional.category, ranges[&conditional.category].start..conditional.value + 1);
-        (range1, range2)
-    } else {
-        panic!("Unknown operation");
-    }
-}
-
-fn find_distinct_combinations(workflows: &Workflows, rules: &[Rule], ranges: CategoryRanges) -> u64 {
-    let rule = &rules[0];
-    println!("Trying {:?}", rule);
-    let Some(ref conditional) = rule.conditional else {
-        println!("  Always {}", rule.when_true);
-        match rule.when_true.as_str() {
-            "A" => return category_combinations(ranges),
-            "R" => return 0,
-            _ => return find_distinct_combinations(workflows, workflows.get(&rule.when_true).unwrap(), ranges),
-        }
-    };
-    let (true_range, false_range) = split_ranges(conditional, ranges);
-    println!("  Is {} {} {}?", conditional.category, conditional.operation, conditional.value);
-    println!("  True range: {:?}", true_range);
-    println!("  False range: {:?}", false_range);
-    match rule.when_true.as_str() {
-        "A" => category_combinations(true_range),
-        "R" => 0,
-        _ => {
-            println!("  Finding combinations for true branch: {}", rule.when_true);
-            let combinations1 = find_distinct_combinations(workflows, workflows.get(&rule.when_true).unwrap(), true_range);
-            println!("  Finding combinations for false branch");
-            let combinations2 = find_distinct_combinations(workflows, &rules[1..], false_range);
-            println!("  Found {} combinations", combinations1 + combinations2);
-            combinations1 + combinations2
-        },
-    }
-}
-
-fn solve_part2(input: &InputType) -> u64 {
-    // TODO: This currently does not pass the sample input
-    let workflows = &input.0;
-    let rules = workflows.get("in").unwrap();
-    let ranges = vec![('x', 1..4001), ('m', 1..4001), ('a', 1..4001), ('s', 1..4001)]
-        .into_iter()
-        .collect::<CategoryRanges>();
-    find_distinct_combinations(workflows, rules, ranges)
+fn solve_part2(tree: &DecisionTree) -> u64 {
+    tree.accepted_boxes(HyperBox::new(tree.categories.len(), RATINGS))
+        .iter()
+        .map(HyperBox::volume)
+        .sum()
 }
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
+
+    // With --dot, print the workflows as a Graphviz digraph instead of solving, highlighting
+    // the path of a part such as "{x=787,m=2655,a=1222,s=2876}" if one follows.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--dot") {
+        let part = args.get(1).map(|part| parse_part(part));
+        print!("{}", workflows_to_dot(&input.0, part.as_ref()));
+        return;
+    }
     println!("Parsed input ({:?})", parse_start.elapsed());
 
+    let categories = find_categories(&input);
+    let tree = DecisionTree::compile(&input.0, &categories, RATINGS)
+        .unwrap_or_else(|error| panic!("Cannot compile workflows: {:?}", error));
+    for (name, index) in &tree.unreachable_rules {
+        println!(
+            "Warning: rule {} of workflow {} can never match",
+            index + 1,
+            name
+        );
+    }
+
     let part1_start = std::time::Instant::now();
-    let part1 = solve_part1(&input);
+    let part1 = solve_part1(&input, &tree);
     println!("Part 1: {} ({:?})", part1, part1_start.elapsed());
 
-    // let part2_start = std::time::Instant::now();
-    // let part2 = solve_part2(&input);
-    // println!("Part 2: {} ({:?})", part2, part2_start.elapsed());
+    let part2_start = std::time::Instant::now();
+    let part2 = solve_part2(&tree);
+    println!("Part 2: {} ({:?})", part2, part2_start.elapsed());
 }
 
 #[cfg(test)]
@@ -252,17 +676,17 @@ mod tests {
 
     #[test]
     fn test_rule_from_str_conditional() {
-        let rule = Rule::from_str("a<2006:qkq");
+        let rule = parse_all(rule(), "a<2006:qkq").unwrap();
         let conditional = rule.conditional.unwrap();
         assert_eq!(conditional.category, 'a');
-        assert_eq!(conditional.operation, '<');
+        assert_eq!(conditional.operation, Operation::Less);
         assert_eq!(conditional.value, 2006);
         assert_eq!(rule.when_true, "qkq");
     }
 
     #[test]
     fn test_rule_from_str_always() {
-        let rule = Rule::from_str("A");
+        let rule = parse_all(rule(), "A").unwrap();
         assert!(rule.conditional.is_none());
         assert_eq!(rule.when_true, "A");
     }
@@ -273,29 +697,51 @@ mod tests {
         assert_eq!(key, "px");
         assert_eq!(rules.len(), 3);
         assert_eq!(rules[0].conditional.as_ref().unwrap().category, 'a');
-        assert_eq!(rules[0].conditional.as_ref().unwrap().operation, '<');
+        assert_eq!(
+            rules[0].conditional.as_ref().unwrap().operation,
+            Operation::Less
+        );
         assert_eq!(rules[0].conditional.as_ref().unwrap().value, 2006);
         assert_eq!(rules[0].when_true, "qkq");
         assert_eq!(rules[1].conditional.as_ref().unwrap().category, 'm');
-        assert_eq!(rules[1].conditional.as_ref().unwrap().operation, '>');
+        assert_eq!(
+            rules[1].conditional.as_ref().unwrap().operation,
+            Operation::Greater
+        );
         assert_eq!(rules[1].conditional.as_ref().unwrap().value, 2090);
         assert_eq!(rules[1].when_true, "A");
         assert_eq!(rules[2].conditional, None);
         assert_eq!(rules[2].when_true, "rfg");
     }
 
+    #[test]
+    fn test_parse_errors() {
+        let error = parse_all(workflow(), "px{a<2006:qkq,m=2090:A,rfg}").unwrap_err();
+        assert_eq!(error.column, 16);
+        assert_eq!(error.expected, "\"}\"");
+        let error = parse_all(part(), "{x=787,m=2655,x=2876}").unwrap_err();
+        assert_eq!(error.column, 1);
+        assert_eq!(error.expected, "at most one rating per category");
+    }
+
     #[test]
     fn test_parse_part() {
         let part = parse_part("{x=787,m=2655,a=1222,s=2876}");
-        assert_eq!(part.x, 787);
-        assert_eq!(part.m, 2655);
-        assert_eq!(part.a, 1222);
-        assert_eq!(part.s, 2876);
+        let ratings: Vec<(char, i32)> = part.ratings.into_iter().collect();
+        assert_eq!(
+            ratings,
+            vec![('a', 1222), ('m', 2655), ('s', 2876), ('x', 787)]
+        );
+
+        let part = parse_part("{q=5}");
+        assert_eq!(part.ratings.get(&'q'), Some(&5));
+        let conditional = parse_all(conditional(), "x<10").unwrap();
+        assert!(!test_conditional(&part, &conditional));
     }
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.0.len(), 11);
         assert!(input.0.contains_key("in"));
         assert_eq!(input.1.len(), 5);
@@ -303,94 +749,233 @@ mod tests {
 
     #[test]
     fn test_is_part_accepted() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        assert!(is_part_accepted(&input.1[0], &input.0));
-        assert!(!is_part_accepted(&input.1[1], &input.0));
-        assert!(is_part_accepted(&input.1[2], &input.0));
-        assert!(!is_part_accepted(&input.1[3], &input.0));
-        assert!(is_part_accepted(&input.1[4], &input.0));
+        let input = parse_input(SAMPLE_INPUT);
+        let tree = compile(&input.0).unwrap();
+        assert!(tree.is_accepted(&input.1[0]));
+        assert!(!tree.is_accepted(&input.1[1]));
+        assert!(tree.is_accepted(&input.1[2]));
+        assert!(!tree.is_accepted(&input.1[3]));
+        assert!(tree.is_accepted(&input.1[4]));
+    }
+
+    fn workflows_from<'a>(lines: &[&'a str]) -> Workflows<'a> {
+        lines.iter().map(|line| parse_workflow(line)).collect()
+    }
+
+    fn compile(workflows: &Workflows) -> Result<DecisionTree, CompileError> {
+        DecisionTree::compile(workflows, &['a', 'm', 's', 'x'], RATINGS)
+    }
+
+    #[test]
+    fn test_compile() {
+        let input = parse_input(SAMPLE_INPUT);
+        let tree = compile(&input.0).unwrap();
+        assert_eq!(tree.names.len(), 11);
+        assert_eq!(tree.names[0], "in");
+        // Accept, reject, and one test for every conditional rule.
+        assert_eq!(tree.nodes.len(), 2 + 14);
+        assert!(tree.unreachable_rules.is_empty());
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let cycle = workflows_from(&["in{x<10:a,R}", "a{m>5:b,A}", "b{a}"]);
+        assert_eq!(
+            compile(&cycle).unwrap_err(),
+            CompileError::Cycle(vec!["a".to_string(), "b".to_string(), "a".to_string()])
+        );
+
+        let unknown = workflows_from(&["in{x<10:a,R}"]);
+        assert_eq!(
+            compile(&unknown).unwrap_err(),
+            CompileError::UnknownWorkflow("a".to_string())
+        );
+
+        let no_fallback = workflows_from(&["in{x<10:A}"]);
+        assert_eq!(
+            compile(&no_fallback).unwrap_err(),
+            CompileError::MissingFallback("in".to_string())
+        );
+
+        let unknown_category = workflows_from(&["in{x<10:a,R}", "a{q>5:R,A}"]);
+        assert_eq!(
+            compile(&unknown_category).unwrap_err(),
+            CompileError::UnknownCategory("a".to_string(), 'q')
+        );
+    }
+
+    #[test]
+    fn test_find_unreachable_rules() {
+        let workflows = workflows_from(&["in{x<10:A,x<5:R,a}", "a{m>3:R,A}", "b{A}"]);
+        assert_eq!(
+            find_unreachable_rules(&workflows, &['a', 'm', 'x'], RATINGS),
+            vec![("b".to_string(), 0), ("in".to_string(), 1)]
+        );
+
+        // Workflow c is reached through in, a and b, but only parts sent by b have x below 5.
+        let shared = workflows_from(&["in{x<5:b,m<10:a,c}", "a{c}", "b{c}", "c{x>4:A,x<5:R,A}"]);
+        assert_eq!(
+            find_unreachable_rules(&shared, &['m', 'x'], RATINGS),
+            vec![("c".to_string(), 2)]
+        );
+        assert_eq!(
+            find_unreachable_rules(&shared, &['m', 'x'], 5..4001),
+            vec![
+                ("b".to_string(), 0),
+                ("c".to_string(), 1),
+                ("c".to_string(), 2),
+                ("in".to_string(), 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conditional_unrated_category() {
+        let part = parse_part("{x=787,m=2655,a=1222,s=2876}");
+        let parse = |input| parse_all(conditional(), input).unwrap();
+        assert!(test_conditional(&part, &parse("x<788")));
+        assert!(!test_conditional(&part, &parse("m>2655")));
+        assert!(!test_conditional(&part, &parse("q<5000")));
+        assert!(!test_conditional(&part, &parse("q>0")));
+    }
+
+    #[test]
+    fn test_display_decision_tree() {
+        let workflows = workflows_from(&["in{x<10:A,a}", "a{m>3:R,A}"]);
+        let tree = compile(&workflows).unwrap();
+        assert_eq!(
+            tree.to_string(),
+            "in: x<10\n  true -> A\n  false -> a: m>3\n    true -> R\n    false -> A\n"
+        );
+    }
+
+    #[test]
+    fn test_find_part_path() {
+        let input = parse_input(SAMPLE_INPUT);
+        assert_eq!(
+            find_part_path(&input.1[0], &input.0),
+            vec![("in", 1), ("qqz", 0), ("qs", 1), ("lnx", 0)]
+        );
+        assert_eq!(
+            find_part_path(&input.1[1], &input.0),
+            vec![("in", 0), ("px", 2), ("rfg", 0), ("gd", 1)]
+        );
+    }
+
+    #[test]
+    fn test_workflows_to_dot() {
+        let workflows = workflows_from(&["in{x<10:A,a}", "a{m>3:R,A}"]);
+        let part = parse_part("{x=20,m=1,a=1,s=1}");
+        let dot = workflows_to_dot(&workflows, Some(&part));
+        assert!(dot.starts_with("digraph workflows {\n"));
+        assert!(dot.contains("  in -> A [label=\"x<10\"];\n"));
+        assert!(dot.contains("  in -> a [label=\"else\", color=red, penwidth=2];\n"));
+        assert!(dot.contains("  a -> R [label=\"m>3\"];\n"));
+        assert!(dot.contains("  a -> A [label=\"else\", color=red, penwidth=2];\n"));
+        assert!(dot.ends_with("}\n"));
+
+        let plain = workflows_to_dot(&workflows, None);
+        assert!(!plain.contains("color=red"));
     }
 
     #[test]
     fn test_part_value() {
-        let part = Part {
-            x: 787,
-            m: 2655,
-            a: 1222,
-            s: 2876,
-        };
+        let part = parse_part("{x=787,m=2655,a=1222,s=2876}");
         assert_eq!(part_value(&part), 7540);
     }
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        let result = solve_part1(&input);
+        let input = parse_input(SAMPLE_INPUT);
+        let tree = compile(&input.0).unwrap();
+        let result = solve_part1(&input, &tree);
         assert_eq!(result, 19114)
     }
 
     #[test]
-    fn test_split_ranges_less_than() {
-        let ranges = vec![('x', 1..4001), ('m', 1..4001), ('a', 1..4001), ('s', 1..4001)]
-            .into_iter()
-            .collect::<CategoryRanges>();
-        let conditional = Conditional {
-            category: 's',
-            operation: '<',
-            value: 1351,
-        };
-        let (range1, range2) = split_ranges(&conditional, ranges);
-        assert_eq!(range1[&'s'], 1..1351);
-        assert_eq!(range2[&'s'], 1351..4001);
+    fn test_split_box_less_than() {
+        let hyper_box = HyperBox::new(4, 1..4001);
+        let (box1, box2) = hyper_box.split(3, Operation::Less, 1351);
+        assert_eq!(box1.ranges[3], RangeSet::from(1..1351));
+        assert_eq!(box2.ranges[3], RangeSet::from(1351..4001));
+        assert_eq!(box1.ranges[0], RangeSet::from(1..4001));
     }
 
     #[test]
-    fn test_split_ranges_greater_than() {
-        let ranges = vec![('x', 1..4001), ('m', 1..4001), ('a', 1..4001), ('s', 1..4001)]
-            .into_iter()
-            .collect::<CategoryRanges>();
-        let conditional = Conditional {
-            category: 'm',
-            operation: '>',
-            value: 2655,
-        };
-        let (range1, range2) = split_ranges(&conditional, ranges);
-        assert_eq!(range1[&'m'], 2656..4001);
-        assert_eq!(range2[&'m'], 1..2656);
+    fn test_split_box_greater_than() {
+        let hyper_box = HyperBox::new(4, 1..4001);
+        let (box1, box2) = hyper_box.split(1, Operation::Greater, 2655);
+        assert_eq!(box1.ranges[1], RangeSet::from(2656..4001));
+        assert_eq!(box2.ranges[1], RangeSet::from(1..2656));
     }
 
     #[test]
-    fn test_category_combinations() {
-        let ranges_empty = vec![('x', 1..0), ('m', 1..0), ('a', 1..0), ('s', 1..0)]
-            .into_iter()
-            .collect::<CategoryRanges>();
-        assert_eq!(category_combinations(ranges_empty), 0);
+    fn test_box_volume() {
+        assert_eq!(HyperBox::new(4, Range { start: 1, end: 0 }).volume(), 0);
+        assert_eq!(HyperBox::new(4, 1..1).volume(), 0);
+        assert_eq!(HyperBox::new(4, 1..2).volume(), 1);
+        assert_eq!(HyperBox::new(4, 1..101).volume(), 100_u64.pow(4));
+        assert_eq!(HyperBox::new(4, 1..4001).volume(), 4000_u64.pow(4));
+        assert_eq!(HyperBox::new(2, 1..4001).volume(), 4000_u64.pow(2));
+    }
 
-        let ranges_zero = vec![('x', 1..1), ('m', 1..1), ('a', 1..1), ('s', 1..1)]
-            .into_iter()
-            .collect::<CategoryRanges>();
-        assert_eq!(category_combinations(ranges_zero), 0);
+    #[test]
+    fn test_find_categories() {
+        let input = parse_input(SAMPLE_INPUT);
+        assert_eq!(find_categories(&input), vec!['a', 'm', 's', 'x']);
+
+        let workflows = workflows_from(&["in{x<10:A,q>5:R,a}", "a{m>3:R,A}"]);
+        assert_eq!(tested_categories(&workflows), vec!['m', 'q', 'x']);
+        assert_eq!(
+            find_categories(&(workflows, Vec::new())),
+            vec!['m', 'q', 'x']
+        );
+
+        // Categories come from the parts even when no rule tests them.
+        let input = parse_input("in{x<10:A,R}\n\n{x=1,m=2,a=3,s=4}\n{x=5,m=6,a=7,s=8}\n");
+        assert_eq!(find_categories(&input), vec!['a', 'm', 's', 'x']);
+    }
 
-        let ranges_one = vec![('x', 1..2), ('m', 1..2), ('a', 1..2), ('s', 1..2)]
-            .into_iter()
-            .collect::<CategoryRanges>();
-        assert_eq!(category_combinations(ranges_one), 1);
+    #[test]
+    #[should_panic(expected = "Part 2 is not rated in the same categories as part 1")]
+    fn test_parse_mismatched_categories() {
+        parse_input("in{A}\n\n{x=1,m=2}\n{x=1,a=2}\n");
+    }
 
-        let ranges_middle = vec![('x', 1..101), ('m', 1..101), ('a', 1..101), ('s', 1..101)]
-            .into_iter()
-            .collect::<CategoryRanges>();
-        assert_eq!(category_combinations(ranges_middle), 100_u64.pow(4));
+    #[test]
+    fn test_untested_categories() {
+        // Only x is tested, but every part is also rated in m, a and s, each of which
+        // can take any of the 4000 ratings.
+        let input = parse_input("in{x<10:A,R}\n\n{x=1,m=2,a=3,s=4}\n");
+        let tree = DecisionTree::compile(&input.0, &find_categories(&input), RATINGS).unwrap();
+        assert_eq!(solve_part2(&tree), 9 * 4000_u64.pow(3));
+        assert_eq!(solve_part1(&input, &tree), 10);
+
+        // Parts rated in other categories work the same way.
+        let input = parse_input("in{q>2:A,R}\n\n{p=1,q=3}\n");
+        let tree = DecisionTree::compile(&input.0, &find_categories(&input), RATINGS).unwrap();
+        assert_eq!(solve_part2(&tree), 3998 * 4000);
+        assert_eq!(solve_part1(&input, &tree), 4);
+    }
 
-        let ranges_max = vec![('x', 1..4001), ('m', 1..4001), ('a', 1..4001), ('s', 1..4001)]
-            .into_iter()
-            .collect::<CategoryRanges>();
-        assert_eq!(category_combinations(ranges_max), 4000_u64.pow(4));
+    #[test]
+    fn test_accepted_boxes() {
+        let workflows = workflows_from(&["in{x<11:A,m>3000:R,a}", "a{m>2000:R,A}"]);
+        let tree = DecisionTree::compile(&workflows, &['m', 'x'], RATINGS).unwrap();
+        let accepted = tree.accepted_boxes(HyperBox::new(2, 1..4001));
+        assert_eq!(accepted.len(), 2);
+        assert_eq!(accepted[0].ranges[1], RangeSet::from(1..11));
+        assert_eq!(accepted[0].volume(), 10 * 4000);
+        assert_eq!(accepted[1].ranges[0], RangeSet::from(1..2001));
+        assert_eq!(accepted[1].ranges[1], RangeSet::from(11..4001));
+        assert_eq!(accepted[1].volume(), 2000 * 3990);
     }
 
     #[test]
     fn test_part2() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
-        let result = solve_part2(&input);
+        let input = parse_input(SAMPLE_INPUT);
+        let tree = compile(&input.0).unwrap();
+        let result = solve_part2(&tree);
         assert_eq!(result, 167_409_079_868_000)
     }
 }
@@ -30,7 +30,7 @@ impl GardenMap {
     }
 }
 
-fn parse_input(input_str: String) -> InputType {
+fn parse_input(input_str: &str) -> InputType {
     let mut grid = Vec::new();
     let mut start = (0, 0);
     for (y, line) in input_str.lines().enumerate() {
@@ -88,7 +88,8 @@ fn solve_part2(input: &InputType) -> SolutionType {
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
     let part1_start = std::time::Instant::now();
@@ -108,7 +109,7 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.grid.len(), 11);
         assert_eq!(input.grid[0].len(), 11);
         assert_eq!(input.start, (5, 5));
@@ -118,7 +119,7 @@ mod tests {
 
     #[test]
     fn test_count_reachable_plots() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(count_reachable_plots(&input, 0), 1);
         assert_eq!(count_reachable_plots(&input, 1), 2);
         assert_eq!(count_reachable_plots(&input, 2), 4);
@@ -192,7 +192,7 @@ impl PathFinder {
     }
 }
 
-fn parse_input(input_str: String) -> InputType {
+fn parse_input(input_str: &str) -> InputType {
     let map = input_str
         .lines()
         .map(|line| line.chars().collect())
@@ -211,7 +211,8 @@ fn solve_part2(input: &InputType) -> SolutionType {
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
     let part1_start = std::time::Instant::now();
@@ -231,7 +232,7 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.map.len(), 23);
         assert_eq!(input.map[0].len(), 23);
         assert_eq!(input.height, 23);
@@ -244,7 +245,7 @@ mod tests {
 
     #[test]
     fn test_get_set_cost() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let mut path_finder = PathFinder::new(input);
         path_finder.set_cost((1, 1), 42);
         assert_eq!(path_finder.get_cost((1, 1)), 42);
@@ -252,7 +253,7 @@ mod tests {
 
     #[test]
     fn test_get_next_node() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let mut path_finder = PathFinder::new(input);
         path_finder.add_point((1, 0), None);
         path_finder.add_point(
@@ -271,7 +272,7 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 94)
     }
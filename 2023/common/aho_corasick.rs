@@ -0,0 +1,183 @@
+// AhoCorasick
+// Finds every occurrence of many patterns in one pass over the text, including overlapping
+// matches. Each pattern carries a value, so a table of words can map straight to results.
+//
+// Include in a day's solution with:
+//   #[path = "../common/aho_corasick.rs"]
+//   mod aho_corasick;
+
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+
+// Matches work on the UTF-8 bytes of the text. A pattern that is itself valid UTF-8 can only
+// match starting and ending on character boundaries, so byte offsets are always safe to slice.
+const ALPHABET: usize = 256;
+
+pub struct AhoCorasick<V> {
+    patterns: Vec<(usize, V)>,
+    // The full transition table: the next state for every state and byte.
+    transitions: Vec<[u32; ALPHABET]>,
+    // The patterns (by index) that end at each state, including those reached via suffixes.
+    outputs: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Match<'a, V> {
+    // Byte offsets into the text.
+    pub start: usize,
+    pub end: usize,
+    pub value: &'a V,
+}
+
+impl<V> AhoCorasick<V> {
+    pub fn new<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = (S, V)>,
+        S: AsRef<str>,
+    {
+        // Build the trie of patterns. 0 marks a missing edge, since no edge leads back to the root.
+        let mut transitions: Vec<[u32; ALPHABET]> = vec![[0; ALPHABET]];
+        let mut outputs: Vec<Vec<usize>> = vec![Vec::new()];
+        let mut stored = Vec::new();
+        for (index, (pattern, value)) in patterns.into_iter().enumerate() {
+            let bytes = pattern.as_ref().as_bytes();
+            assert!(!bytes.is_empty(), "Empty pattern");
+            let mut state = 0;
+            for &byte in bytes {
+                if transitions[state][byte as usize] == 0 {
+                    transitions.push([0; ALPHABET]);
+                    outputs.push(Vec::new());
+                    transitions[state][byte as usize] = (transitions.len() - 1) as u32;
+                }
+                state = transitions[state][byte as usize] as usize;
+            }
+            outputs[state].push(index);
+            stored.push((bytes.len(), value));
+        }
+
+        // Breadth first, fill in each missing edge with the edge from the longest proper suffix
+        // that is also in the trie, and inherit that suffix's outputs.
+        let mut fail = vec![0_usize; transitions.len()];
+        let mut queue: VecDeque<usize> = transitions[0]
+            .iter()
+            .filter(|&&next| next != 0)
+            .map(|&next| next as usize)
+            .collect();
+        while let Some(state) = queue.pop_front() {
+            let inherited = outputs[fail[state]].clone();
+            outputs[state].extend(inherited);
+            let fallbacks = transitions[fail[state]];
+            for (transition, &fallback) in transitions[state].iter_mut().zip(&fallbacks) {
+                let next = *transition as usize;
+                if next == 0 {
+                    *transition = fallback;
+                } else {
+                    fail[next] = fallback as usize;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        Self {
+            patterns: stored,
+            transitions,
+            outputs,
+        }
+    }
+
+    // Returns every match in the text, overlapping ones included, ordered by where they end.
+    // Matches ending at the same place are ordered longest first.
+    pub fn find_overlapping<'a>(&'a self, text: &str) -> Vec<Match<'a, V>> {
+        let mut result = Vec::new();
+        let mut state = 0;
+        for (i, &byte) in text.as_bytes().iter().enumerate() {
+            state = self.transitions[state][byte as usize] as usize;
+            // A state's own pattern comes before those inherited from shorter suffixes.
+            for &index in &self.outputs[state] {
+                let (length, ref value) = self.patterns[index];
+                result.push(Match {
+                    start: i + 1 - length,
+                    end: i + 1,
+                    value,
+                });
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+#[path = "rng.rs"]
+mod rng;
+
+#[cfg(test)]
+mod tests {
+    use super::rng::Rng;
+    use super::*;
+
+    fn spans<V>(matches: &[Match<V>]) -> Vec<(usize, usize)> {
+        matches.iter().map(|m| (m.start, m.end)).collect()
+    }
+
+    #[test]
+    fn test_overlapping_matches() {
+        let matcher = AhoCorasick::new(vec![("he", 1), ("she", 2), ("his", 3), ("hers", 4)]);
+        let matches = matcher.find_overlapping("ushers");
+        let values: Vec<i32> = matches.iter().map(|m| *m.value).collect();
+        assert_eq!(values, vec![2, 1, 4]);
+        assert_eq!(spans(&matches), vec![(1, 4), (2, 4), (2, 6)]);
+        assert!(matcher.find_overlapping("").is_empty());
+        assert!(matcher.find_overlapping("xyz").is_empty());
+    }
+
+    #[test]
+    fn test_utf8() {
+        let matcher = AhoCorasick::new(vec![("één", 1), ("twee", 2), ("ü", 3)]);
+        let text = "ééénütwee";
+        let matches = matcher.find_overlapping(text);
+        let found: Vec<(&str, i32)> = matches
+            .iter()
+            .map(|m| (&text[m.start..m.end], *m.value))
+            .collect();
+        assert_eq!(found, vec![("één", 1), ("ü", 3), ("twee", 2)]);
+    }
+
+    // A word of up to `max_length` letters, including a multi-byte one.
+    fn random_word(rng: &mut Rng, max_length: u64) -> String {
+        let letters = ['a', 'b', 'é'];
+        let length = 1 + rng.below(max_length);
+        (0..length)
+            .map(|_| letters[rng.below(3) as usize])
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_naive_search() {
+        let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..300 {
+            let patterns: Vec<String> = (0..1 + rng.below(6))
+                .map(|_| random_word(&mut rng, 4))
+                .collect();
+            let text = random_word(&mut rng, 30);
+            let matcher = AhoCorasick::new(patterns.iter().enumerate().map(|(i, p)| (p, i)));
+            let mut found: Vec<(usize, usize, usize)> = matcher
+                .find_overlapping(&text)
+                .iter()
+                .map(|m| (m.start, m.end, *m.value))
+                .collect();
+            let mut expected = Vec::new();
+            for (i, pattern) in patterns.iter().enumerate() {
+                for (offset, _) in text.char_indices() {
+                    if text[offset..].starts_with(pattern.as_str()) {
+                        expected.push((offset, offset + pattern.len(), i));
+                    }
+                }
+            }
+            expected.sort_unstable();
+            expected.dedup();
+            found.sort_unstable();
+            assert_eq!(found, expected);
+        }
+    }
+}
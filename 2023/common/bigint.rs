@@ -0,0 +1,495 @@
+// BigInt
+// An arbitrary-precision signed integer for answers that do not fit in a primitive type.
+// A day can opt in with `type SolutionType = BigInt;` and keep printing it with `{}`.
+//
+// Include in a day's solution with:
+//   #[path = "../common/bigint.rs"]
+//   mod bigint;
+
+#![allow(dead_code)]
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Rem, Sub};
+use std::str::FromStr;
+
+// Sign and magnitude, with the magnitude stored as base 2^32 limbs, least significant first.
+// The magnitude never has trailing zero limbs and zero is never negative,
+// so equal values always have the same representation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseBigIntError;
+
+fn trim(limbs: &mut Vec<u32>) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0_u64;
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+// Requires a >= b.
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0_i64;
+    for (i, limb) in a.iter().enumerate() {
+        let mut diff = *limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 1 << 32;
+            borrow = 1;
+        }
+        result.push(diff as u32);
+    }
+    trim(&mut result);
+    result
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut result = vec![0_u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0_u64;
+        for (j, y) in b.iter().enumerate() {
+            let product = *x as u64 * *y as u64 + result[i + j] as u64 + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+    trim(&mut result);
+    result
+}
+
+// Divides by a single limb, returning the quotient and remainder.
+fn div_rem_small(a: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0_u32; a.len()];
+    let mut remainder = 0_u64;
+    for i in (0..a.len()).rev() {
+        let current = (remainder << 32) | a[i] as u64;
+        quotient[i] = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+    trim(&mut quotient);
+    (quotient, remainder as u32)
+}
+
+// Binary long division. Slow for huge numbers but fine for puzzle answers.
+fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    assert!(!b.is_empty(), "Division by zero");
+    if cmp_magnitude(a, b) == Ordering::Less {
+        return (Vec::new(), a.to_vec());
+    }
+    if b.len() == 1 {
+        let (quotient, remainder) = div_rem_small(a, b[0]);
+        let mut remainder = vec![remainder];
+        trim(&mut remainder);
+        return (quotient, remainder);
+    }
+    let mut quotient = vec![0_u32; a.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+    for bit in (0..a.len() * 32).rev() {
+        // remainder = remainder * 2 + next bit of a
+        let mut carry = (a[bit / 32] >> (bit % 32)) & 1;
+        for limb in remainder.iter_mut() {
+            let next_carry = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next_carry;
+        }
+        if carry > 0 {
+            remainder.push(carry);
+        }
+        if cmp_magnitude(&remainder, b) != Ordering::Less {
+            remainder = sub_magnitude(&remainder, b);
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    trim(&mut quotient);
+    (quotient, remainder)
+}
+
+impl BigInt {
+    fn from_parts(negative: bool, mut limbs: Vec<u32>) -> Self {
+        trim(&mut limbs);
+        let negative = negative && !limbs.is_empty();
+        Self { negative, limbs }
+    }
+
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::from_parts(false, self.limbs.clone())
+    }
+
+    // Truncating division like Rust's primitive integers:
+    // the quotient rounds toward zero and the remainder has the sign of the dividend.
+    pub fn div_rem(&self, other: &Self) -> (Self, Self) {
+        let (quotient, remainder) = div_rem_magnitude(&self.limbs, &other.limbs);
+        (
+            Self::from_parts(self.negative != other.negative, quotient),
+            Self::from_parts(self.negative, remainder),
+        )
+    }
+
+    // Returns the value if it fits in an i128.
+    pub fn to_i128(&self) -> Option<i128> {
+        if self.limbs.len() > 4 {
+            return None;
+        }
+        let magnitude = self
+            .limbs
+            .iter()
+            .rev()
+            .fold(0_u128, |acc, limb| (acc << 32) | *limb as u128);
+        if self.negative {
+            if magnitude <= i128::MAX as u128 + 1 {
+                Some((magnitude as i128).wrapping_neg())
+            } else {
+                None
+            }
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+}
+
+macro_rules! impl_from_signed {
+    ($($t:ty),*) => {
+        $(impl From<$t> for BigInt {
+            fn from(value: $t) -> Self {
+                let mut magnitude = (value as i128).unsigned_abs();
+                let mut limbs = Vec::new();
+                while magnitude > 0 {
+                    limbs.push(magnitude as u32);
+                    magnitude >>= 32;
+                }
+                Self::from_parts(value < 0, limbs)
+            }
+        })*
+    };
+}
+
+macro_rules! impl_from_unsigned {
+    ($($t:ty),*) => {
+        $(impl From<$t> for BigInt {
+            fn from(value: $t) -> Self {
+                let mut magnitude = value as u128;
+                let mut limbs = Vec::new();
+                while magnitude > 0 {
+                    limbs.push(magnitude as u32);
+                    magnitude >>= 32;
+                }
+                Self::from_parts(false, limbs)
+            }
+        })*
+    };
+}
+
+impl_from_signed!(i8, i16, i32, i64, i128, isize);
+impl_from_unsigned!(u8, u16, u32, u64, u128, usize);
+
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        if digits.is_empty() {
+            return Err(ParseBigIntError);
+        }
+        let mut limbs: Vec<u32> = Vec::new();
+        for c in digits.chars() {
+            let digit = c.to_digit(10).ok_or(ParseBigIntError)?;
+            // limbs = limbs * 10 + digit
+            let mut carry = digit as u64;
+            for limb in limbs.iter_mut() {
+                let value = *limb as u64 * 10 + carry;
+                *limb = value as u32;
+                carry = value >> 32;
+            }
+            if carry > 0 {
+                limbs.push(carry as u32);
+            }
+        }
+        Ok(Self::from_parts(negative, limbs))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.pad_integral(true, "", "0");
+        }
+        // Peel off nine decimal digits at a time.
+        let mut chunks = Vec::new();
+        let mut magnitude = self.limbs.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = div_rem_small(&magnitude, 1_000_000_000);
+            chunks.push(remainder);
+            magnitude = quotient;
+        }
+        let mut digits = chunks.pop().unwrap().to_string();
+        for chunk in chunks.iter().rev() {
+            digits.push_str(&format!("{:09}", chunk));
+        }
+        f.pad_integral(!self.negative, "", &digits)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.limbs, &other.limbs),
+            (true, true) => cmp_magnitude(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.limbs.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_magnitude(&self.limbs, &other.limbs));
+        }
+        match cmp_magnitude(&self.limbs, &other.limbs) {
+            Ordering::Less => {
+                BigInt::from_parts(other.negative, sub_magnitude(&other.limbs, &self.limbs))
+            }
+            _ => BigInt::from_parts(self.negative, sub_magnitude(&self.limbs, &other.limbs)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &(-other)
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::from_parts(
+            self.negative != other.negative,
+            mul_magnitude(&self.limbs, &other.limbs),
+        )
+    }
+}
+
+impl Div for &BigInt {
+    type Output = BigInt;
+
+    fn div(self, other: &BigInt) -> BigInt {
+        self.div_rem(other).0
+    }
+}
+
+impl Rem for &BigInt {
+    type Output = BigInt;
+
+    fn rem(self, other: &BigInt) -> BigInt {
+        self.div_rem(other).1
+    }
+}
+
+// Owned operands forward to the reference implementations.
+macro_rules! forward_owned {
+    ($($trait:ident $method:ident),*) => {
+        $(impl $trait for BigInt {
+            type Output = BigInt;
+
+            fn $method(self, other: BigInt) -> BigInt {
+                (&self).$method(&other)
+            }
+        })*
+    };
+}
+
+forward_owned!(Add add, Sub sub, Mul mul, Div div, Rem rem);
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        -&self
+    }
+}
+
+impl AddAssign for BigInt {
+    fn add_assign(&mut self, other: BigInt) {
+        *self = &*self + &other;
+    }
+}
+
+impl Sum for BigInt {
+    fn sum<I: Iterator<Item = BigInt>>(iter: I) -> Self {
+        iter.fold(BigInt::zero(), |acc, value| acc + value)
+    }
+}
+
+#[cfg(test)]
+#[path = "rng.rs"]
+mod rng;
+
+#[cfg(test)]
+mod tests {
+    use super::rng::Rng;
+    use super::*;
+
+    fn big(s: &str) -> BigInt {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(big("0").to_string(), "0");
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(big("+42").to_string(), "42");
+        assert_eq!(big("-1000000000").to_string(), "-1000000000");
+        assert_eq!(big("000123").to_string(), "123");
+        let huge = "-123456789012345678901234567890123456789012345678901234567890";
+        assert_eq!(big(huge).to_string(), huge);
+        assert_eq!("".parse::<BigInt>(), Err(ParseBigIntError));
+        assert_eq!("-".parse::<BigInt>(), Err(ParseBigIntError));
+        assert_eq!("12a".parse::<BigInt>(), Err(ParseBigIntError));
+        assert_eq!(format!("{:>5}", BigInt::from(-42)), "  -42");
+    }
+
+    #[test]
+    fn test_arithmetic_beyond_i128() {
+        let max = BigInt::from(u128::MAX);
+        assert_eq!(
+            (&max * &max).to_string(),
+            "115792089237316195423570985008687907852589419931798687112530834793049593217025"
+        );
+        assert_eq!(
+            (&max + &BigInt::from(1)).to_string(),
+            "340282366920938463463374607431768211456"
+        );
+        let factorial: BigInt = (1..=30)
+            .map(BigInt::from)
+            .fold(BigInt::from(1), |acc, n| acc * n);
+        assert_eq!(factorial.to_string(), "265252859812191058636308480000000");
+        let (quotient, remainder) = (&max * &max).div_rem(&max);
+        assert_eq!(quotient, max);
+        assert!(remainder.is_zero());
+        assert_eq!(BigInt::from(i128::MIN).to_i128(), Some(i128::MIN));
+        assert_eq!((&max * &max).to_i128(), None);
+    }
+
+    #[test]
+    fn test_sum_and_ordering() {
+        let total: BigInt = vec![1, -5, 10].into_iter().map(BigInt::from).sum();
+        assert_eq!(total, BigInt::from(6));
+        assert!(BigInt::from(-10) < BigInt::from(-2));
+        assert!(BigInt::from(-1) < BigInt::zero());
+        assert!(big("100000000000000000000000") > BigInt::from(i64::MAX));
+    }
+
+    // A random value between -2^63 and 2^63 with a random number of significant bits.
+    fn random_value(rng: &mut Rng) -> i128 {
+        let value = (rng.next_u64() >> rng.below(64)) as i128;
+        if rng.below(2) == 0 {
+            value
+        } else {
+            -value
+        }
+    }
+
+    #[test]
+    fn test_properties_match_i128() {
+        let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..5000 {
+            let (a, b) = (random_value(&mut rng), random_value(&mut rng));
+            let (x, y) = (BigInt::from(a), BigInt::from(b));
+            assert_eq!(x.to_string(), a.to_string());
+            assert_eq!(big(&a.to_string()), x);
+            assert_eq!((&x + &y).to_i128(), Some(a + b));
+            assert_eq!((&x - &y).to_i128(), Some(a - b));
+            assert_eq!((&x * &y).to_i128(), Some(a * b));
+            assert_eq!(x.cmp(&y), a.cmp(&b));
+            if b != 0 {
+                let (quotient, remainder) = x.div_rem(&y);
+                assert_eq!(quotient.to_i128(), Some(a / b));
+                assert_eq!(remainder.to_i128(), Some(a % b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_division_properties() {
+        let mut rng = Rng::new(0x2545_f491_4f6c_dd1d);
+        for _ in 0..500 {
+            let a = &(&BigInt::from(random_value(&mut rng))
+                * &BigInt::from(random_value(&mut rng)))
+                * &BigInt::from(random_value(&mut rng));
+            let b = &BigInt::from(random_value(&mut rng)) * &BigInt::from(random_value(&mut rng));
+            if b.is_zero() {
+                continue;
+            }
+            let (quotient, remainder) = a.div_rem(&b);
+            assert_eq!(&(&quotient * &b) + &remainder, a);
+            assert!(remainder.abs() < b.abs());
+            assert!(remainder.is_zero() || remainder.is_negative() == a.is_negative());
+        }
+    }
+}
@@ -0,0 +1,273 @@
+// Grid
+// Zero-copy rotated, flipped and transposed views over a grid of cells.
+// An algorithm written for one orientation (say, scanning rows left to right)
+// can run in every other orientation by handing it a different view.
+//
+// Include in a day's solution with:
+//   #[path = "../common/grid.rs"]
+//   mod grid;
+
+#![allow(dead_code)]
+
+pub type Grid<T> = Vec<Vec<T>>;
+
+// A view maps its own (row, col) to a cell of the underlying grid with
+//   source = origin + row * row_step + col * col_step
+// so every rotation and flip is just a different origin and pair of steps.
+// Views of an empty grid can still be turned and flipped, and stay empty.
+pub struct GridView<'a, T> {
+    cells: &'a [Vec<T>],
+    height: usize,
+    width: usize,
+    origin: (isize, isize),
+    row_step: (isize, isize),
+    col_step: (isize, isize),
+}
+
+// Derived impls would require T: Clone, but a view only holds a reference.
+impl<'a, T> Clone for GridView<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for GridView<'a, T> {}
+
+fn offset(point: (isize, isize), step: (isize, isize), times: usize) -> (isize, isize) {
+    let times = times as isize;
+    (point.0 + step.0 * times, point.1 + step.1 * times)
+}
+
+fn negate(step: (isize, isize)) -> (isize, isize) {
+    (-step.0, -step.1)
+}
+
+impl<'a, T> GridView<'a, T> {
+    pub fn new(cells: &'a [Vec<T>]) -> Self {
+        Self {
+            cells,
+            height: cells.len(),
+            width: cells.first().map_or(0, |row| row.len()),
+            origin: (0, 0),
+            row_step: (1, 0),
+            col_step: (0, 1),
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    // Returns the (row, col) in the underlying grid that this view shows at (row, col).
+    pub fn source(&self, row: usize, col: usize) -> (usize, usize) {
+        assert!(
+            row < self.height && col < self.width,
+            "Position outside view"
+        );
+        let (r, c) = offset(offset(self.origin, self.row_step, row), self.col_step, col);
+        (r as usize, c as usize)
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> &'a T {
+        let (r, c) = self.source(row, col);
+        &self.cells[r][c]
+    }
+
+    pub fn row(&self, row: usize) -> impl Iterator<Item = &'a T> + 'a {
+        let view = *self;
+        (0..self.width).map(move |col| view.get(row, col))
+    }
+
+    // Swaps rows and columns, so the first column of the grid is the first row of the view.
+    pub fn transpose(&self) -> Self {
+        Self {
+            height: self.width,
+            width: self.height,
+            row_step: self.col_step,
+            col_step: self.row_step,
+            ..*self
+        }
+    }
+
+    // The view as seen after turning the grid a quarter turn clockwise:
+    // the first column of the grid, read bottom to top, becomes the first row.
+    pub fn rotate_clockwise(&self) -> Self {
+        Self {
+            height: self.width,
+            width: self.height,
+            origin: offset(self.origin, self.row_step, self.height.saturating_sub(1)),
+            row_step: self.col_step,
+            col_step: negate(self.row_step),
+            ..*self
+        }
+    }
+
+    pub fn rotate_counter_clockwise(&self) -> Self {
+        Self {
+            height: self.width,
+            width: self.height,
+            origin: offset(self.origin, self.col_step, self.width.saturating_sub(1)),
+            row_step: negate(self.col_step),
+            col_step: self.row_step,
+            ..*self
+        }
+    }
+
+    pub fn rotate_180(&self) -> Self {
+        self.rotate_clockwise().rotate_clockwise()
+    }
+
+    // Mirrors the view left to right.
+    pub fn flip_horizontal(&self) -> Self {
+        Self {
+            origin: offset(self.origin, self.col_step, self.width.saturating_sub(1)),
+            col_step: negate(self.col_step),
+            ..*self
+        }
+    }
+
+    // Mirrors the view top to bottom.
+    pub fn flip_vertical(&self) -> Self {
+        Self {
+            origin: offset(self.origin, self.row_step, self.height.saturating_sub(1)),
+            row_step: negate(self.row_step),
+            ..*self
+        }
+    }
+
+    pub fn to_grid(self) -> Grid<T>
+    where
+        T: Clone,
+    {
+        (0..self.height)
+            .map(|row| self.row(row).cloned().collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Grid<char> {
+        vec!["abc".chars().collect(), "def".chars().collect()]
+    }
+
+    fn to_strings(view: &GridView<char>) -> Vec<String> {
+        (0..view.height())
+            .map(|row| view.row(row).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_views() {
+        let grid = sample();
+        let view = GridView::new(&grid);
+        assert_eq!(to_strings(&view), vec!["abc", "def"]);
+        assert_eq!(to_strings(&view.transpose()), vec!["ad", "be", "cf"]);
+        assert_eq!(to_strings(&view.rotate_clockwise()), vec!["da", "eb", "fc"]);
+        assert_eq!(
+            to_strings(&view.rotate_counter_clockwise()),
+            vec!["cf", "be", "ad"]
+        );
+        assert_eq!(to_strings(&view.rotate_180()), vec!["fed", "cba"]);
+        assert_eq!(to_strings(&view.flip_horizontal()), vec!["cba", "fed"]);
+        assert_eq!(to_strings(&view.flip_vertical()), vec!["def", "abc"]);
+        assert_eq!(
+            view.rotate_clockwise().to_grid(),
+            vec![vec!['d', 'a'], vec!['e', 'b'], vec!['f', 'c']]
+        );
+    }
+
+    #[test]
+    fn test_source() {
+        let grid = sample();
+        let view = GridView::new(&grid);
+        assert_eq!(view.source(1, 2), (1, 2));
+        assert_eq!(view.transpose().source(2, 1), (1, 2));
+        assert_eq!(view.rotate_clockwise().source(0, 0), (1, 0));
+        assert_eq!(view.rotate_counter_clockwise().source(0, 0), (0, 2));
+        assert_eq!(view.rotate_180().source(0, 0), (1, 2));
+        assert_eq!(view.flip_horizontal().source(1, 0), (1, 2));
+        assert_eq!(view.flip_vertical().source(1, 0), (0, 0));
+    }
+
+    #[test]
+    fn test_empty_views() {
+        let grid: Grid<char> = Vec::new();
+        let view = GridView::new(&grid);
+        for turned in [
+            view.transpose(),
+            view.rotate_clockwise(),
+            view.rotate_counter_clockwise(),
+            view.rotate_180(),
+            view.flip_horizontal(),
+            view.flip_vertical(),
+        ] {
+            assert_eq!(turned.height() * turned.width(), 0);
+            assert!(turned.to_grid().is_empty());
+        }
+        let empty_rows: Grid<char> = vec![Vec::new(), Vec::new()];
+        let view = GridView::new(&empty_rows).rotate_clockwise();
+        assert_eq!((view.height(), view.width()), (0, 2));
+    }
+
+    // Checks that two views show the same cells of the grid in the same places.
+    fn same_mapping(a: &GridView<char>, b: &GridView<char>) -> bool {
+        a.height() == b.height()
+            && a.width() == b.width()
+            && (0..a.height())
+                .all(|row| (0..a.width()).all(|col| a.source(row, col) == b.source(row, col)))
+    }
+
+    #[test]
+    fn test_compositions() {
+        let grid: Grid<char> = ["abcd", "efgh", "ijkl"]
+            .iter()
+            .map(|row| row.chars().collect())
+            .collect();
+        let views = {
+            let view = GridView::new(&grid);
+            vec![
+                view,
+                view.transpose(),
+                view.rotate_clockwise(),
+                view.rotate_counter_clockwise(),
+                view.rotate_180(),
+                view.flip_horizontal(),
+                view.flip_vertical(),
+            ]
+        };
+        for view in &views {
+            let identity = view.rotate_clockwise().rotate_counter_clockwise();
+            assert!(same_mapping(&identity, view));
+            assert!(same_mapping(&view.transpose().transpose(), view));
+            assert!(same_mapping(&view.rotate_180().rotate_180(), view));
+            assert!(same_mapping(
+                &view
+                    .rotate_clockwise()
+                    .rotate_clockwise()
+                    .rotate_clockwise(),
+                &view.rotate_counter_clockwise()
+            ));
+            assert!(same_mapping(
+                &view.rotate_clockwise(),
+                &view.transpose().flip_horizontal()
+            ));
+            assert!(same_mapping(
+                &view.rotate_180(),
+                &view.flip_horizontal().flip_vertical()
+            ));
+            for row in 0..view.height() {
+                for col in 0..view.width() {
+                    let (r, c) = view.source(row, col);
+                    assert_eq!(view.get(row, col), &grid[r][c]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,361 @@
+// Number theory
+// gcd, lcm, extended Euclid, modular arithmetic, CRT and integer square roots
+// for any primitive integer width. Operations that can overflow are checked.
+//
+// Include in a day's solution with:
+//   #[path = "../common/numtheory.rs"]
+//   mod numtheory;
+
+#![allow(dead_code)]
+
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+pub trait Integer:
+    Copy
+    + Ord
+    + Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    fn abs_value(self) -> Self;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+}
+
+pub trait SignedInteger: Integer + Neg<Output = Self> {}
+
+macro_rules! impl_unsigned {
+    ($($t:ty),*) => {
+        $(impl Integer for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            fn abs_value(self) -> Self {
+                self
+            }
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+        })*
+    };
+}
+
+macro_rules! impl_signed {
+    ($($t:ty),*) => {
+        $(impl Integer for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            fn abs_value(self) -> Self {
+                self.abs()
+            }
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+        }
+
+        impl SignedInteger for $t {})*
+    };
+}
+
+impl_unsigned!(u8, u16, u32, u64, u128, usize);
+impl_signed!(i8, i16, i32, i64, i128, isize);
+
+pub fn gcd<T: Integer>(a: T, b: T) -> T {
+    let (mut a, mut b) = (a.abs_value(), b.abs_value());
+    while b != T::ZERO {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+// Returns None if the result does not fit in T.
+pub fn lcm<T: Integer>(a: T, b: T) -> Option<T> {
+    if a == T::ZERO || b == T::ZERO {
+        return Some(T::ZERO);
+    }
+    (a.abs_value() / gcd(a, b)).checked_mul(b.abs_value())
+}
+
+// Returns (gcd, x, y) such that a * x + b * y = gcd.
+pub fn extended_gcd<T: SignedInteger>(a: T, b: T) -> (T, T, T) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (T::ONE, T::ZERO);
+    let (mut old_y, mut y) = (T::ZERO, T::ONE);
+    while r != T::ZERO {
+        let q = old_r / r;
+        let next_r = old_r - q * r;
+        old_r = r;
+        r = next_r;
+        let next_x = old_x - q * x;
+        old_x = x;
+        x = next_x;
+        let next_y = old_y - q * y;
+        old_y = y;
+        y = next_y;
+    }
+    if old_r < T::ZERO {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+// Returns the non-negative remainder of a divided by a positive modulus.
+pub fn rem_euclid<T: Integer>(a: T, m: T) -> T {
+    let r = a % m;
+    if r < T::ZERO {
+        r + m
+    } else {
+        r
+    }
+}
+
+// Adds two values in 0..m without overflowing, even when m is close to T's maximum.
+pub fn add_mod<T: Integer>(a: T, b: T, m: T) -> T {
+    if a >= m - b {
+        a - (m - b)
+    } else {
+        a + b
+    }
+}
+
+// Multiplies two values in 0..m, falling back to double-and-add if the product would overflow.
+pub fn mul_mod<T: Integer>(a: T, b: T, m: T) -> T {
+    if let Some(product) = a.checked_mul(b) {
+        return product % m;
+    }
+    let two = T::ONE + T::ONE;
+    let mut result = T::ZERO;
+    let mut a = a % m;
+    let mut b = b;
+    while b > T::ZERO {
+        if b % two == T::ONE {
+            result = add_mod(result, a, m);
+        }
+        a = add_mod(a, a, m);
+        b = b / two;
+    }
+    result
+}
+
+pub fn mod_pow<T: Integer>(base: T, exponent: T, m: T) -> T {
+    let two = T::ONE + T::ONE;
+    let mut result = T::ONE % m;
+    let mut base = rem_euclid(base, m);
+    let mut exponent = exponent;
+    while exponent > T::ZERO {
+        if exponent % two == T::ONE {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exponent = exponent / two;
+    }
+    result
+}
+
+// Returns x such that a * x = 1 (mod m), or None if a and m are not coprime.
+pub fn mod_inverse<T: SignedInteger>(a: T, m: T) -> Option<T> {
+    let (g, x, _) = extended_gcd(rem_euclid(a, m), m);
+    if g == T::ONE {
+        Some(rem_euclid(x, m))
+    } else {
+        None
+    }
+}
+
+// Combines x = r1 (mod m1) and x = r2 (mod m2) into x = r (mod lcm(m1, m2)).
+// The moduli do not need to be coprime. Returns None if there is no solution
+// or if the combined modulus does not fit in T.
+pub fn crt_merge<T: SignedInteger>((r1, m1): (T, T), (r2, m2): (T, T)) -> Option<(T, T)> {
+    let r1 = rem_euclid(r1, m1);
+    let r2 = rem_euclid(r2, m2);
+    let (g, p, _) = extended_gcd(m1, m2);
+    let diff = r2 - r1;
+    if diff % g != T::ZERO {
+        return None;
+    }
+    let n = m2 / g;
+    let modulus = (m1 / g).checked_mul(m2)?;
+    let k = mul_mod(rem_euclid(diff / g, n), rem_euclid(p, n), n);
+    Some((r1 + m1 * k, modulus))
+}
+
+// Solves a system of congruences (remainder, modulus) with possibly non-coprime moduli.
+pub fn crt<T: SignedInteger>(congruences: &[(T, T)]) -> Option<(T, T)> {
+    congruences
+        .iter()
+        .try_fold((T::ZERO, T::ONE), |acc, congruence| {
+            crt_merge(acc, *congruence)
+        })
+}
+
+// Returns the largest r such that r * r <= n.
+pub fn isqrt<T: Integer>(n: T) -> T {
+    assert!(n >= T::ZERO, "isqrt of a negative number");
+    let two = T::ONE + T::ONE;
+    let (mut lo, mut hi) = (T::ZERO, n / two + T::ONE);
+    while lo < hi {
+        let mid = hi - (hi - lo) / two;
+        match mid.checked_mul(mid) {
+            Some(square) if square <= n => lo = mid,
+            _ => hi = mid - T::ONE,
+        }
+    }
+    lo
+}
+
+#[cfg(test)]
+#[path = "rng.rs"]
+mod rng;
+
+#[cfg(test)]
+mod tests {
+    use super::rng::Rng;
+    use super::*;
+
+    #[test]
+    fn test_gcd() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(18_u8, 12), 6);
+        assert_eq!(gcd(-12_i32, 18), 6);
+        assert_eq!(gcd(0_u64, 7), 7);
+        assert_eq!(gcd(0_u64, 0), 0);
+    }
+
+    #[test]
+    fn test_lcm() {
+        assert_eq!(lcm(1, 1), Some(1));
+        assert_eq!(lcm(1001, 1001), Some(1001));
+        assert_eq!(lcm(2, 3), Some(6));
+        assert_eq!(lcm(3, 2), Some(6));
+        assert_eq!(lcm(2, 4), Some(4));
+        assert_eq!(lcm(4, 2), Some(4));
+        assert_eq!(lcm(3, 4), Some(12));
+        assert_eq!(lcm(4, 3), Some(12));
+        assert_eq!(lcm(5, 15), Some(15));
+        assert_eq!(lcm(15, 5), Some(15));
+        assert_eq!(lcm(12, 18), Some(36));
+        assert_eq!(lcm(18, 12), Some(36));
+        assert_eq!(lcm(0, 5), Some(0));
+    }
+
+    #[test]
+    fn test_lcm_overflow() {
+        assert_eq!(lcm(200_u8, 3), None);
+        assert_eq!(lcm(u64::MAX, u64::MAX - 1), None);
+        assert_eq!(lcm(u64::MAX, u64::MAX), Some(u64::MAX));
+        assert_eq!(lcm(1_u64 << 62, 1 << 63), Some(1 << 63));
+    }
+
+    #[test]
+    fn test_extended_gcd() {
+        assert_eq!(extended_gcd(240, 46), (2, -9, 47));
+        assert_eq!(extended_gcd(-4_i64, 6), (2, 1, 1));
+    }
+
+    #[test]
+    fn test_mod_inverse() {
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(-3, 11), Some(7));
+        assert_eq!(mod_inverse(4, 8), None);
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt_merge((2, 3), (3, 5)), Some((8, 15)));
+        assert_eq!(crt_merge((0, 2), (0, 6)), Some((0, 6)));
+        assert_eq!(crt_merge((3, 4), (5, 6)), Some((11, 12)));
+        assert_eq!(crt_merge((0, 2), (3, 6)), None);
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt::<i64>(&[]), Some((0, 1)));
+        assert_eq!(crt(&[(1_i8, 100), (2, 99)]), None);
+    }
+
+    #[test]
+    fn test_mod_pow() {
+        assert_eq!(mod_pow(2, 10, 1000), 24);
+        assert_eq!(mod_pow(3_u8, 200, 251), 149);
+        assert_eq!(mod_pow(5, 0, 1), 0);
+        assert_eq!(mod_pow(u64::MAX - 1, 3, u64::MAX), u64::MAX - 1);
+    }
+
+    #[test]
+    fn test_isqrt() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(1), 1);
+        assert_eq!(isqrt(15), 3);
+        assert_eq!(isqrt(16), 4);
+        assert_eq!(isqrt(255_u8), 15);
+        assert_eq!(isqrt(u64::MAX), u32::MAX as u64);
+        assert_eq!(isqrt(i128::MAX), 13_043_817_825_332_782_212);
+    }
+
+    #[test]
+    fn test_properties() {
+        let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..5000 {
+            let a = rng.next_u64() >> rng.below(64);
+            let b = rng.next_u64() >> rng.below(64);
+
+            let g = gcd(a, b);
+            match lcm(a, b) {
+                Some(l) if a != 0 && b != 0 => {
+                    assert_eq!(l % a, 0);
+                    assert_eq!(l % b, 0);
+                    assert_eq!(g as u128 * l as u128, a as u128 * b as u128);
+                }
+                Some(l) => assert_eq!(l, 0),
+                None => assert!(a as u128 / g as u128 * b as u128 > u64::MAX as u128),
+            }
+
+            let (g, x, y) = extended_gcd(a as i128, b as i128);
+            assert_eq!(g, gcd(a, b) as i128);
+            assert_eq!(a as i128 * x + b as i128 * y, g);
+
+            let m = b.max(2);
+            let expected = (a % m) as u128 * (b % m) as u128 % m as u128;
+            assert_eq!(mul_mod(a % m, b % m, m) as u128, expected);
+
+            let root = isqrt(a) as u128;
+            assert!(root * root <= a as u128);
+            assert!((root + 1) * (root + 1) > a as u128);
+
+            let exponent = rng.below(40);
+            let naive = (0..exponent).fold(1 % m, |acc, _| mul_mod(acc, a % m, m));
+            assert_eq!(mod_pow(a, exponent, m), naive);
+
+            let m = m as i128;
+            if let Some(inverse) = mod_inverse(a as i128, m) {
+                assert_eq!(mul_mod(rem_euclid(a as i128, m), inverse, m), 1 % m);
+            } else {
+                assert_ne!(gcd(a as i128, m), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_crt_properties() {
+        let mut rng = Rng::new(0x2545_f491_4f6c_dd1d);
+        for _ in 0..1000 {
+            let moduli: Vec<i64> = (0..3).map(|_| rng.below(60) as i64 + 1).collect();
+            let congruences: Vec<(i64, i64)> =
+                moduli.iter().map(|m| (rng.below(100) as i64, *m)).collect();
+            let expected_modulus = moduli.iter().fold(1, |acc, m| lcm(acc, *m).unwrap());
+            let brute_force =
+                (0..expected_modulus).find(|x| congruences.iter().all(|(r, m)| (x - r) % m == 0));
+            match crt(&congruences) {
+                Some((remainder, modulus)) => {
+                    assert_eq!(modulus, expected_modulus);
+                    assert_eq!(Some(remainder), brute_force);
+                }
+                None => assert_eq!(brute_force, None),
+            }
+        }
+    }
+}
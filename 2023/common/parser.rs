@@ -0,0 +1,525 @@
+// Parser
+// Small parser combinators for describing puzzle input formats declaratively.
+// A parser is any function from the remaining input to a value and the input left over,
+// so grammars are built by combining the functions below, for example:
+//   let card = preceded(pair(literal("Card"), spaces1()), unsigned::<u32>());
+//   let cards = parse_all(lines(card), input)?;
+//
+// Include in a day's solution with:
+//   #[path = "../common/parser.rs"]
+//   mod parser;
+
+#![allow(dead_code)]
+
+use std::fmt;
+use std::str::FromStr;
+
+// Why a parser failed, and how much input was left at that point.
+// The remaining length stays meaningful as parsers nest, and is turned into a line
+// and column once the whole input is known.
+#[derive(Debug, PartialEq)]
+pub struct Failure {
+    pub expected: String,
+    pub remaining: usize,
+}
+
+pub type ParseResult<'a, T> = Result<(T, &'a str), Failure>;
+
+pub trait Parser<'a, T> {
+    fn parse(&self, input: &'a str) -> ParseResult<'a, T>;
+}
+
+impl<'a, T, F> Parser<'a, T> for F
+where
+    F: Fn(&'a str) -> ParseResult<'a, T>,
+{
+    fn parse(&self, input: &'a str) -> ParseResult<'a, T> {
+        self(input)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    // Both counted from 1, with the column in characters.
+    pub line: usize,
+    pub column: usize,
+    pub expected: String,
+    pub found: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: expected {}, found {:?}",
+            self.line, self.column, self.expected, self.found
+        )
+    }
+}
+
+impl ParseError {
+    fn new(input: &str, failure: Failure) -> Self {
+        let offset = input.len() - failure.remaining;
+        let before = &input[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let found = input[offset..].lines().next().unwrap_or("");
+        Self {
+            line,
+            column: input[line_start..offset].chars().count() + 1,
+            expected: failure.expected,
+            found: found.chars().take(20).collect(),
+        }
+    }
+}
+
+fn fail<'a, T>(expected: &str, input: &'a str) -> ParseResult<'a, T> {
+    Err(Failure {
+        expected: expected.to_string(),
+        remaining: input.len(),
+    })
+}
+
+// Runs the parser over the whole input, allowing only trailing whitespace after it.
+pub fn parse_all<'a, T>(parser: impl Parser<'a, T>, input: &'a str) -> Result<T, ParseError> {
+    let result = parser.parse(input).and_then(|(value, rest)| {
+        if rest.trim().is_empty() {
+            Ok(value)
+        } else {
+            // Point at the first unexpected character rather than the whitespace before it.
+            Err(Failure {
+                expected: "end of input".to_string(),
+                remaining: rest.trim_start().len(),
+            })
+        }
+    });
+    result.map_err(|failure| ParseError::new(input, failure))
+}
+
+// Matches the given text exactly.
+pub fn literal<'a>(expected: &'static str) -> impl Parser<'a, &'a str> {
+    move |input: &'a str| match input.strip_prefix(expected) {
+        Some(rest) => Ok((&input[..expected.len()], rest)),
+        None => fail(&format!("{:?}", expected), input),
+    }
+}
+
+// Matches the longest non-empty run of characters satisfying the predicate.
+pub fn take_while1<'a>(
+    predicate: fn(char) -> bool,
+    name: &'static str,
+) -> impl Parser<'a, &'a str> {
+    move |input: &'a str| {
+        let end = input.find(|c| !predicate(c)).unwrap_or(input.len());
+        if end == 0 {
+            fail(name, input)
+        } else {
+            Ok((&input[..end], &input[end..]))
+        }
+    }
+}
+
+// Matches a single character satisfying the predicate.
+pub fn character<'a>(predicate: fn(char) -> bool, name: &'static str) -> impl Parser<'a, char> {
+    move |input: &'a str| match input.chars().next() {
+        Some(c) if predicate(c) => Ok((c, &input[c.len_utf8()..])),
+        _ => fail(name, input),
+    }
+}
+
+// Letters, digits and underscores.
+pub fn identifier<'a>() -> impl Parser<'a, &'a str> {
+    take_while1(|c| c.is_alphanumeric() || c == '_', "identifier")
+}
+
+pub fn unsigned<'a, T: FromStr>() -> impl Parser<'a, T> {
+    move |input: &'a str| {
+        let (digits, rest) =
+            take_while1(|c| c.is_ascii_digit(), "unsigned integer").parse(input)?;
+        match digits.parse() {
+            Ok(value) => Ok((value, rest)),
+            Err(_) => fail("integer in range", input),
+        }
+    }
+}
+
+// An integer with an optional leading sign.
+pub fn signed<'a, T: FromStr>() -> impl Parser<'a, T> {
+    move |input: &'a str| {
+        let sign = if input.starts_with('-') || input.starts_with('+') {
+            1
+        } else {
+            0
+        };
+        let end = input[sign..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(input.len(), |i| i + sign);
+        if end == sign {
+            return fail("signed integer", input);
+        }
+        match input[..end].parse() {
+            Ok(value) => Ok((value, &input[end..])),
+            Err(_) => fail("integer in range", input),
+        }
+    }
+}
+
+// Zero or more spaces.
+pub fn spaces<'a>() -> impl Parser<'a, ()> {
+    move |input: &'a str| Ok(((), input.trim_start_matches(' ')))
+}
+
+// One or more spaces.
+pub fn spaces1<'a>() -> impl Parser<'a, ()> {
+    map(take_while1(|c| c == ' ', "space"), |_| ())
+}
+
+pub fn newline<'a>() -> impl Parser<'a, ()> {
+    map(either(literal("\r\n"), literal("\n")), |_| ())
+}
+
+// The empty line between two sections of input.
+pub fn blank_line<'a>() -> impl Parser<'a, ()> {
+    map(pair(newline(), newline()), |_| ())
+}
+
+pub fn map<'a, A, B>(parser: impl Parser<'a, A>, f: impl Fn(A) -> B) -> impl Parser<'a, B> {
+    move |input: &'a str| parser.parse(input).map(|(value, rest)| (f(value), rest))
+}
+
+// Like map, but the function can reject the value with a description of what was expected.
+pub fn map_result<'a, A, B>(
+    parser: impl Parser<'a, A>,
+    f: impl Fn(A) -> Result<B, String>,
+) -> impl Parser<'a, B> {
+    move |input: &'a str| {
+        let (value, rest) = parser.parse(input)?;
+        match f(value) {
+            Ok(value) => Ok((value, rest)),
+            Err(expected) => fail(&expected, input),
+        }
+    }
+}
+
+pub fn pair<'a, A, B>(
+    first: impl Parser<'a, A>,
+    second: impl Parser<'a, B>,
+) -> impl Parser<'a, (A, B)> {
+    move |input: &'a str| {
+        let (a, rest) = first.parse(input)?;
+        let (b, rest) = second.parse(rest)?;
+        Ok(((a, b), rest))
+    }
+}
+
+// Runs both parsers and keeps the second value.
+pub fn preceded<'a, A, B>(
+    first: impl Parser<'a, A>,
+    second: impl Parser<'a, B>,
+) -> impl Parser<'a, B> {
+    map(pair(first, second), |(_, b)| b)
+}
+
+// Runs both parsers and keeps the first value.
+pub fn terminated<'a, A, B>(
+    first: impl Parser<'a, A>,
+    second: impl Parser<'a, B>,
+) -> impl Parser<'a, A> {
+    map(pair(first, second), |(a, _)| a)
+}
+
+pub fn delimited<'a, A, B, C>(
+    open: impl Parser<'a, A>,
+    parser: impl Parser<'a, B>,
+    close: impl Parser<'a, C>,
+) -> impl Parser<'a, B> {
+    preceded(open, terminated(parser, close))
+}
+
+// Tries the first parser, then the second. If both fail, reports whichever got further.
+pub fn either<'a, T>(first: impl Parser<'a, T>, second: impl Parser<'a, T>) -> impl Parser<'a, T> {
+    move |input: &'a str| match first.parse(input) {
+        Ok(result) => Ok(result),
+        Err(first_failure) => second.parse(input).map_err(|second_failure| {
+            if first_failure.remaining < second_failure.remaining {
+                first_failure
+            } else {
+                second_failure
+            }
+        }),
+    }
+}
+
+// Matches one of the given words and returns its value, trying longer words first.
+pub fn one_of<'a, T: Clone>(choices: &'a [(&'static str, T)]) -> impl Parser<'a, T> {
+    move |input: &'a str| {
+        let best = choices
+            .iter()
+            .filter(|(word, _)| input.starts_with(word))
+            .max_by_key(|(word, _)| word.len());
+        match best {
+            Some((word, value)) => Ok((value.clone(), &input[word.len()..])),
+            None => {
+                let words: Vec<String> = choices
+                    .iter()
+                    .map(|(word, _)| format!("{:?}", word))
+                    .collect();
+                fail(&format!("one of {}", words.join(", ")), input)
+            }
+        }
+    }
+}
+
+pub fn optional<'a, T>(parser: impl Parser<'a, T>) -> impl Parser<'a, Option<T>> {
+    move |input: &'a str| match parser.parse(input) {
+        Ok((value, rest)) => Ok((Some(value), rest)),
+        Err(_) => Ok((None, input)),
+    }
+}
+
+// Zero or more repetitions.
+pub fn many<'a, T>(parser: impl Parser<'a, T>) -> impl Parser<'a, Vec<T>> {
+    move |mut input: &'a str| {
+        let mut result = Vec::new();
+        while let Ok((value, rest)) = parser.parse(input) {
+            if rest.len() == input.len() {
+                break;
+            }
+            result.push(value);
+            input = rest;
+        }
+        Ok((result, input))
+    }
+}
+
+// Zero or more items with a separator between each pair. A separator commits to another
+// item, so a bad item after one is reported as an error rather than ending the list.
+pub fn separated<'a, T, S>(
+    parser: impl Parser<'a, T>,
+    separator: impl Parser<'a, S>,
+) -> impl Parser<'a, Vec<T>> {
+    move |input: &'a str| {
+        let mut result = Vec::new();
+        let mut input = match parser.parse(input) {
+            Ok((value, rest)) => {
+                result.push(value);
+                rest
+            }
+            // An item that fails part way through is an error rather than an empty list.
+            Err(failure) if failure.remaining < input.len() => return Err(failure),
+            Err(_) => return Ok((result, input)),
+        };
+        while let Ok((_, rest)) = separator.parse(input) {
+            let (value, rest) = parser.parse(rest)?;
+            result.push(value);
+            input = rest;
+        }
+        Ok((result, input))
+    }
+}
+
+// One or more items with a separator between each pair.
+pub fn separated1<'a, T, S>(
+    parser: impl Parser<'a, T>,
+    separator: impl Parser<'a, S>,
+) -> impl Parser<'a, Vec<T>> {
+    let list = separated(parser, separator);
+    move |input: &'a str| {
+        let (result, rest) = list.parse(input)?;
+        if result.is_empty() {
+            return fail("at least one item", input);
+        }
+        Ok((result, rest))
+    }
+}
+
+// Consecutive non-blank lines, each parsed completely by the given parser. Stops before
+// a blank line, so sections of input can follow one another. Errors inside a line are
+// reported where they happen rather than at the start of the line.
+pub fn lines<'a, T>(parser: impl Parser<'a, T>) -> impl Parser<'a, Vec<T>> {
+    move |mut input: &'a str| {
+        let mut result = Vec::new();
+        loop {
+            let end = input.find('\n').unwrap_or(input.len());
+            let line = input[..end].trim_end_matches('\r');
+            if line.trim().is_empty() {
+                return Ok((result, input));
+            }
+            let after_line = &input[end..];
+            let adjust = |failure: Failure| Failure {
+                remaining: failure.remaining + input.len() - line.len(),
+                ..failure
+            };
+            let (value, rest) = parser.parse(line).map_err(adjust)?;
+            if !rest.trim_end().is_empty() {
+                return Err(adjust(Failure {
+                    expected: "end of line".to_string(),
+                    remaining: rest.len(),
+                }));
+            }
+            result.push(value);
+            match after_line.strip_prefix('\n') {
+                Some(next) if !next.trim_start_matches('\r').starts_with('\n') => input = next,
+                _ => return Ok((result, after_line)),
+            }
+        }
+    }
+}
+
+// Parses each non-blank line of the input completely, one at a time as the iterator is
+// advanced, so the values can be used without collecting them first. Errors are located
+// within the whole input, and the remaining lines can still be read after one.
+pub fn parse_lines<'a, T: 'a>(
+    parser: impl Parser<'a, T> + 'a,
+    input: &'a str,
+) -> impl Iterator<Item = Result<T, ParseError>> + 'a {
+    let mut start = 0;
+    input.split('\n').filter_map(move |raw_line| {
+        let line = raw_line.trim_end_matches('\r');
+        let after_line = input.len() - start - line.len();
+        start += raw_line.len() + 1;
+        if line.trim().is_empty() {
+            return None;
+        }
+        let failure = match parser.parse(line) {
+            Ok((value, rest)) if rest.trim_end().is_empty() => return Some(Ok(value)),
+            Ok((_, rest)) => Failure {
+                expected: "end of line".to_string(),
+                remaining: rest.len(),
+            },
+            Err(failure) => failure,
+        };
+        let failure = Failure {
+            remaining: failure.remaining + after_line,
+            ..failure
+        };
+        Some(Err(ParseError::new(input, failure)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primitives() {
+        assert_eq!(literal("ab").parse("abc"), Ok(("ab", "c")));
+        assert!(literal("ab").parse("ba").is_err());
+        assert_eq!(unsigned::<u32>().parse("123x"), Ok((123, "x")));
+        assert!(unsigned::<u8>().parse("300").is_err());
+        assert!(unsigned::<u32>().parse("-1").is_err());
+        assert_eq!(signed::<i32>().parse("-45,"), Ok((-45, ",")));
+        assert_eq!(signed::<i32>().parse("+7"), Ok((7, "")));
+        assert!(signed::<i32>().parse("-").is_err());
+        assert_eq!(identifier().parse("qkq_1:A"), Ok(("qkq_1", ":A")));
+        assert_eq!(
+            character(|c| c.is_alphabetic(), "letter").parse("été"),
+            Ok(('é', "té"))
+        );
+        assert_eq!(spaces().parse("  x"), Ok(((), "x")));
+        assert!(spaces1().parse("x").is_err());
+    }
+
+    #[test]
+    fn test_combinators() {
+        let list = delimited(
+            literal("("),
+            separated(identifier(), literal(", ")),
+            literal(")"),
+        );
+        assert_eq!(list.parse("(BBB, CCC)!"), Ok((vec!["BBB", "CCC"], "!")));
+        assert_eq!(list.parse("()"), Ok((vec![], "")));
+        assert!(separated1(unsigned::<u32>(), literal(","))
+            .parse("x")
+            .is_err());
+        let failure = separated(unsigned::<u32>(), literal(","))
+            .parse("1,2,x")
+            .unwrap_err();
+        assert_eq!(failure.remaining, 1);
+
+        let colours = [("red", 0), ("green", 1), ("greenish", 2)];
+        let colour = one_of(&colours);
+        assert_eq!(colour.parse("greenish blue"), Ok((2, " blue")));
+        assert_eq!(colour.parse("red"), Ok((0, "")));
+
+        let numbers = many(preceded(spaces1(), unsigned::<u32>()));
+        assert_eq!(numbers.parse(" 1  2 3 |"), Ok((vec![1, 2, 3], " |")));
+        assert_eq!(optional(literal("-")).parse("5"), Ok((None, "5")));
+
+        let even = map_result(unsigned::<u32>(), |n| {
+            if n % 2 == 0 {
+                Ok(n)
+            } else {
+                Err("even number".to_string())
+            }
+        });
+        assert_eq!(even.parse("4"), Ok((4, "")));
+        assert!(even.parse("3").is_err());
+    }
+
+    #[test]
+    fn test_either_reports_furthest_failure() {
+        let parser = either(
+            pair(literal("a"), literal("b")),
+            pair(literal("x"), literal("y")),
+        );
+        let failure = parser.parse("ac").unwrap_err();
+        assert_eq!(failure.expected, "\"b\"");
+        assert_eq!(failure.remaining, 1);
+    }
+
+    #[test]
+    fn test_sections_and_errors() {
+        let input = "a=1\nb=2\n\nx y\n";
+        let assignment = pair(terminated(identifier(), literal("=")), unsigned::<u32>());
+        let words = separated1(identifier(), spaces1());
+        let grammar = pair(terminated(lines(assignment), blank_line()), lines(words));
+        let (assignments, words) = parse_all(grammar, input).unwrap();
+        assert_eq!(assignments, vec![("a", 1), ("b", 2)]);
+        assert_eq!(words, vec![vec!["x", "y"]]);
+
+        let assignment = pair(terminated(identifier(), literal("=")), unsigned::<u32>());
+        let error = parse_all(lines(assignment), "a=1\r\nbé=x2\nc=3").unwrap_err();
+        assert_eq!(
+            error,
+            ParseError {
+                line: 2,
+                column: 4,
+                expected: "unsigned integer".to_string(),
+                found: "x2".to_string(),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "line 2, column 4: expected unsigned integer, found \"x2\""
+        );
+
+        let error = parse_all(lines(unsigned::<u32>()), "1\n2 3\n").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 2));
+        assert_eq!(error.expected, "end of line");
+
+        let error = parse_all(lines(unsigned::<u32>()), "1\n\n2\n").unwrap_err();
+        assert_eq!((error.line, error.column), (3, 1));
+        assert_eq!(error.expected, "end of input");
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let values: Vec<_> = parse_lines(unsigned::<u32>(), "1\r\n\n 2\n3 4\né=5\n6").collect();
+        assert_eq!(values.len(), 5);
+        assert_eq!(values[0], Ok(1));
+        assert_eq!(values[4], Ok(6));
+        let error = values[1].as_ref().unwrap_err();
+        assert_eq!((error.line, error.column), (3, 1));
+        assert_eq!(error.expected, "unsigned integer");
+        let error = values[2].as_ref().unwrap_err();
+        assert_eq!((error.line, error.column), (4, 2));
+        assert_eq!(error.expected, "end of line");
+        let error = values[3].as_ref().unwrap_err();
+        assert_eq!(
+            (error.line, error.column, error.found.as_str()),
+            (5, 1, "é=5")
+        );
+        assert_eq!(parse_lines(unsigned::<u32>(), "").count(), 0);
+    }
+}
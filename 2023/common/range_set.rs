@@ -0,0 +1,286 @@
+// RangeSet
+// A set of values stored as sorted, disjoint, half-open ranges.
+//
+// Include in a day's solution with:
+//   #[path = "../common/range_set.rs"]
+//   mod range_set;
+
+#![allow(dead_code)]
+
+use std::cmp::max;
+use std::cmp::min;
+use std::iter::FromIterator;
+use std::iter::Sum;
+use std::ops::Range;
+use std::ops::Sub;
+
+// Ranges are kept sorted, non-empty, and never overlapping or touching,
+// so two sets containing the same values always compare equal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RangeSet<T> {
+    ranges: Vec<Range<T>>,
+}
+
+impl<T: Copy + Ord> RangeSet<T> {
+    pub fn new() -> Self {
+        Self { ranges: Vec::new() }
+    }
+
+    pub fn ranges(&self) -> &[Range<T>] {
+        &self.ranges
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Range<T>> {
+        self.ranges.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    // Returns the total number of values in the set.
+    pub fn len(&self) -> T
+    where
+        T: Sub<Output = T> + Sum<T>,
+    {
+        self.ranges.iter().map(|r| r.end - r.start).sum()
+    }
+
+    // Returns the smallest range that contains every value in the set.
+    pub fn span(&self) -> Option<Range<T>> {
+        let first = self.ranges.first()?;
+        let last = self.ranges.last()?;
+        Some(first.start..last.end)
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        let index = self.ranges.partition_point(|r| r.end <= *value);
+        index < self.ranges.len() && self.ranges[index].start <= *value
+    }
+
+    // Adds a range to the set, merging it with any ranges it overlaps or touches.
+    pub fn insert(&mut self, range: Range<T>) {
+        if range.start >= range.end {
+            return;
+        }
+        let lo = self.ranges.partition_point(|r| r.end < range.start);
+        let hi = self.ranges.partition_point(|r| r.start <= range.end);
+        let mut start = range.start;
+        let mut end = range.end;
+        if lo < hi {
+            start = min(start, self.ranges[lo].start);
+            end = max(end, self.ranges[hi - 1].end);
+        }
+        self.ranges.splice(lo..hi, std::iter::once(start..end));
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        for range in &other.ranges {
+            result.insert(range.clone());
+        }
+        result
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let a = &self.ranges[i];
+            let b = &other.ranges[j];
+            let start = max(a.start, b.start);
+            let end = min(a.end, b.end);
+            if start < end {
+                ranges.push(start..end);
+            }
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Self { ranges }
+    }
+
+    // Returns the values in this set that are not in the other set.
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut ranges = Vec::new();
+        let mut j = 0;
+        for range in &self.ranges {
+            let mut start = range.start;
+            while j < other.ranges.len() && other.ranges[j].end <= start {
+                j += 1;
+            }
+            let mut k = j;
+            while k < other.ranges.len() && other.ranges[k].start < range.end {
+                if other.ranges[k].start > start {
+                    ranges.push(start..other.ranges[k].start);
+                }
+                start = max(start, other.ranges[k].end);
+                k += 1;
+            }
+            if start < range.end {
+                ranges.push(start..range.end);
+            }
+        }
+        Self { ranges }
+    }
+
+    // Splits the set into the values less than `at` and the values greater than or equal to it.
+    pub fn split_at(&self, at: T) -> (Self, Self) {
+        let mut below = Vec::new();
+        let mut above = Vec::new();
+        for range in &self.ranges {
+            if range.end <= at {
+                below.push(range.clone());
+            } else if range.start >= at {
+                above.push(range.clone());
+            } else {
+                below.push(range.start..at);
+                above.push(at..range.end);
+            }
+        }
+        (Self { ranges: below }, Self { ranges: above })
+    }
+}
+
+impl<T: Copy + Ord> Default for RangeSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Copy + Ord> From<Range<T>> for RangeSet<T> {
+    fn from(range: Range<T>) -> Self {
+        let mut set = Self::new();
+        set.insert(range);
+        set
+    }
+}
+
+impl<T: Copy + Ord> FromIterator<Range<T>> for RangeSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for range in iter {
+            set.insert(range);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+#[path = "rng.rs"]
+mod rng;
+
+#[cfg(test)]
+mod tests {
+    use super::rng::Rng;
+    use super::*;
+
+    #[test]
+    fn test_insert_merges_ranges() {
+        let mut set = RangeSet::new();
+        set.insert(10..20);
+        set.insert(30..40);
+        assert_eq!(set.ranges(), &[10..20, 30..40]);
+
+        set.insert(20..25);
+        assert_eq!(set.ranges(), &[10..25, 30..40]);
+
+        set.insert(5..35);
+        assert_eq!(set, RangeSet::from(5..40));
+
+        set.insert(50..50);
+        assert_eq!(set, RangeSet::from(5..40));
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a: RangeSet<i32> = vec![0..10, 20..30].into_iter().collect();
+        let b = RangeSet::from(5..25);
+        assert_eq!(a.union(&b), RangeSet::from(0..30));
+        assert_eq!(a.intersection(&b).ranges(), &[5..10, 20..25]);
+        assert_eq!(a.difference(&b).ranges(), &[0..5, 25..30]);
+        assert_eq!(b.difference(&a), RangeSet::from(10..20));
+    }
+
+    #[test]
+    fn test_split_at() {
+        let set: RangeSet<i32> = vec![0..10, 20..30].into_iter().collect();
+        let (below, above) = set.split_at(25);
+        assert_eq!(below.ranges(), &[0..10, 20..25]);
+        assert_eq!(above, RangeSet::from(25..30));
+
+        let (below, above) = set.split_at(15);
+        assert_eq!(below, RangeSet::from(0..10));
+        assert_eq!(above, RangeSet::from(20..30));
+    }
+
+    #[test]
+    fn test_len_span_contains() {
+        let set: RangeSet<i64> = vec![0..10, 20..30].into_iter().collect();
+        assert_eq!(set.len(), 20);
+        assert_eq!(set.span(), Some(0..30));
+        assert!(set.contains(&0));
+        assert!(set.contains(&29));
+        assert!(!set.contains(&10));
+        assert!(!set.contains(&30));
+        assert_eq!(RangeSet::<i64>::new().span(), None);
+    }
+
+    // Builds a random set over 0..64 along with a bitmask of the same values.
+    fn random_set(rng: &mut Rng) -> (RangeSet<u64>, u64) {
+        let mut set = RangeSet::new();
+        let mut mask = 0_u64;
+        for _ in 0..rng.below(6) {
+            let start = rng.below(64);
+            let end = start + rng.below(64 - start + 1);
+            set.insert(start..end);
+            for value in start..end {
+                mask |= 1 << value;
+            }
+        }
+        (set, mask)
+    }
+
+    fn to_mask(set: &RangeSet<u64>) -> u64 {
+        (0..64)
+            .filter(|value| set.contains(value))
+            .fold(0, |mask, value| mask | 1 << value)
+    }
+
+    fn is_canonical(set: &RangeSet<u64>) -> bool {
+        set.ranges().iter().all(|r| r.start < r.end)
+            && set.ranges().windows(2).all(|w| w[0].end < w[1].start)
+    }
+
+    #[test]
+    fn test_properties_match_bitmask_model() {
+        let mut rng = Rng::new(0x2545_f491_4f6c_dd1d);
+        for _ in 0..2000 {
+            let (a, a_mask) = random_set(&mut rng);
+            let (b, b_mask) = random_set(&mut rng);
+            assert_eq!(to_mask(&a), a_mask);
+            assert_eq!(a.len(), a_mask.count_ones() as u64);
+
+            let union = a.union(&b);
+            let intersection = a.intersection(&b);
+            let difference = a.difference(&b);
+            assert!(is_canonical(&union));
+            assert!(is_canonical(&intersection));
+            assert!(is_canonical(&difference));
+            assert_eq!(to_mask(&union), a_mask | b_mask);
+            assert_eq!(to_mask(&intersection), a_mask & b_mask);
+            assert_eq!(to_mask(&difference), a_mask & !b_mask);
+            assert_eq!(union, b.union(&a));
+            assert_eq!(intersection, b.intersection(&a));
+
+            let at = rng.below(65);
+            let (below, above) = a.split_at(at);
+            let below_mask = if at == 64 { u64::MAX } else { (1 << at) - 1 };
+            assert_eq!(to_mask(&below), a_mask & below_mask);
+            assert_eq!(to_mask(&above), a_mask & !below_mask);
+            assert_eq!(below.union(&above), a);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+// Rng
+// A small xorshift generator so randomised tests are repeatable without external crates.
+//
+// Include in a day's or a shared module's tests with:
+//   #[cfg(test)]
+//   #[path = "../common/rng.rs"]
+//   mod rng;
+// (or `#[path = "rng.rs"]` from another file in `common/`), then `use super::rng::Rng;`
+// inside the tests module.
+
+#![allow(dead_code)]
+
+pub struct Rng(u64);
+
+impl Rng {
+    // The seed must not be zero, or every value will be zero.
+    pub fn new(seed: u64) -> Self {
+        assert!(seed != 0, "Seed must not be zero");
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // A value from 0 up to but not including `n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeatable() {
+        let mut a = Rng::new(0x2545_f491_4f6c_dd1d);
+        let mut b = Rng::new(0x2545_f491_4f6c_dd1d);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+            assert!(a.below(7) < 7);
+            b.below(7);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+// Sections
+// Splits puzzle input into sections separated by blank lines, borrowing from the input
+// rather than copying lines. Copes with CRLF line endings, trailing whitespace, and
+// blank lines that contain only spaces.
+//
+// Include in a day's solution with:
+//   #[path = "../common/sections.rs"]
+//   mod sections;
+
+#![allow(dead_code)]
+
+pub struct Sections<'a> {
+    remaining: &'a str,
+}
+
+// Each section runs from the start of its first line to the end of its last line,
+// without the trailing whitespace or line ending.
+impl<'a> Iterator for Sections<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let input = self.remaining;
+        let mut start = None;
+        let mut end = 0;
+        let mut offset = 0;
+        for line in input.split_inclusive('\n') {
+            let content = line.trim_end();
+            if content.is_empty() {
+                if start.is_some() {
+                    break;
+                }
+            } else {
+                start = start.or(Some(offset));
+                end = offset + content.len();
+            }
+            offset += line.len();
+        }
+        self.remaining = &input[offset..];
+        start.map(|start| &input[start..end])
+    }
+}
+
+pub fn sections(input: &str) -> Sections<'_> {
+    Sections { remaining: input }
+}
+
+// The lines of a section, each without its line ending or trailing whitespace.
+pub fn section_lines(section: &str) -> impl Iterator<Item = &str> {
+    section.lines().map(str::trim_end)
+}
+
+// Parses section `index` (counting from 0) as a whole.
+pub fn parse_section<'a, T>(input: &'a str, index: usize, parse: impl FnOnce(&'a str) -> T) -> T {
+    match sections(input).nth(index) {
+        Some(section) => parse(section),
+        None => panic!("Input has no section {}", index),
+    }
+}
+
+// Parses each line of section `index` (counting from 0).
+pub fn parse_section_lines<'a, T>(
+    input: &'a str,
+    index: usize,
+    parse: impl FnMut(&'a str) -> T,
+) -> Vec<T> {
+    parse_section(input, index, |section| {
+        section_lines(section).map(parse).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sections() {
+        let input = "a\nb\n\nc\n\n\nd e\nf\n";
+        assert_eq!(
+            sections(input).collect::<Vec<_>>(),
+            vec!["a\nb", "c", "d e\nf"]
+        );
+        assert_eq!(sections("").count(), 0);
+        assert_eq!(sections("\n\n  \n").count(), 0);
+        assert_eq!(sections("\n\na\n").collect::<Vec<_>>(), vec!["a"]);
+        assert_eq!(sections("  a\n b").collect::<Vec<_>>(), vec!["  a\n b"]);
+    }
+
+    #[test]
+    fn test_crlf_and_trailing_whitespace() {
+        let input = "a \r\nb\r\n \t\r\nc\r\n\r\n";
+        assert_eq!(sections(input).collect::<Vec<_>>(), vec!["a \r\nb", "c"]);
+        let lines: Vec<&str> = sections(input).flat_map(section_lines).collect();
+        assert_eq!(lines, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_parse_section() {
+        let input = "seeds: 1 2\n\n10\n20\n\n30\n";
+        assert_eq!(parse_section(input, 0, |s| s.len()), 10);
+        let numbers = parse_section_lines(input, 1, |line| line.parse::<u32>().unwrap());
+        assert_eq!(numbers, vec![10, 20]);
+        assert_eq!(
+            parse_section_lines(input, 2, |line| line.to_string()),
+            vec!["30"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Input has no section 3")]
+    fn test_missing_section() {
+        parse_section("a\n\nb\n\nc", 3, |s| s.len());
+    }
+}
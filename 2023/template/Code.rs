@@ -4,11 +4,13 @@
 use std::fs::read_to_string;
 use std::panic;
 
-type InputType = Vec<String>;
+type InputType<'a> = Vec<&'a str>;
+// Answers that can overflow may use `BigInt` from ../common/bigint.rs instead.
 type SolutionType = i32;
 
-fn parse_input(input_str: String) -> InputType {
-    input_str.lines().map(str::to_string).collect()
+// Input types may borrow from the input text, which main() keeps alive.
+fn parse_input(input_str: &str) -> InputType<'_> {
+    input_str.lines().collect()
 }
 
 fn solve_part1(input: &InputType) -> SolutionType {
@@ -21,7 +23,8 @@ fn solve_part2(input: &InputType) -> SolutionType {
 
 fn main() {
     let parse_start = std::time::Instant::now();
-    let input = parse_input(read_to_string("input.txt").unwrap());
+    let input_str = read_to_string("input.txt").unwrap();
+    let input = parse_input(&input_str);
     println!("Parsed input ({:?})", parse_start.elapsed());
 
     let part1_start = std::time::Instant::now();
@@ -41,13 +44,13 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         assert_eq!(input.len(), 3);
     }
 
     #[test]
     fn test_part1() {
-        let input = parse_input(SAMPLE_INPUT.to_string());
+        let input = parse_input(SAMPLE_INPUT);
         let result = solve_part1(&input);
         assert_eq!(result, 42)
     }